@@ -1,5 +1,6 @@
-use spacetimedb::{table, reducer, ReducerContext, Table};
+use spacetimedb::{table, reducer, ReducerContext, ScheduleAt, SpacetimeType, Table, Timestamp};
 use serde::{Deserialize, Serialize};
+use std::time::Duration;
 
 // ======== Database Schema ========
 
@@ -11,6 +12,12 @@ pub struct User {
     pub session_id: String,
     pub role: String, // "user" or "admin"
     pub connected_at: i64,
+    pub connected: bool, // Cleared when the client disconnects
+    pub seat: Option<u32>, // Breakout table/seat number assigned by an admin
+    pub last_created_poll_at: Option<i64>,
+    pub last_seen: i64, // Join time while connected, disconnect time afterwards
+    pub platform: Option<String>, // Client platform reported on join, one of PLATFORMS
+    pub vote_weight: u32, // Results count each of this user's votes this many times
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -21,6 +28,28 @@ pub struct Poll {
     pub question: String,
     pub is_active: bool,
     pub created_at: i64,
+    pub activated_at: Option<i64>, // Set the last time the poll was activated
+    pub anonymous_reports: bool, // Reports leave voters out; Vote rows still name them
+    pub archived: bool, // Hidden from the running order; can't be activated
+    pub allow_change: bool, // Whether voters may change their vote
+    pub public_ballots: bool, // Anyone may list who voted for what
+    pub closed_at: Option<i64>, // Set when an open poll is closed; cleared on reopen
+    pub close_reason: Option<String>, // e.g. "time expired", "admin ended", "auto-closed"
+    pub allow_write_ins: bool, // Voters may add their own options
+    pub scheduled_open_at: Option<i64>, // Votes are rejected until this time
+    pub correct_option_id: Option<u64>, // Quiz answer, if the poll has one
+    pub max_votes: Option<u32>, // Only the first N voters are admitted
+    pub created_by: Option<String>, // None for polls created before this was tracked
+    pub creator_can_vote: bool,
+    pub group_id: Option<u64>, // Related polls (e.g. tournament rounds) share a group
+    pub requires_poll_id: Option<u64>, // Must be completed before this poll can be activated
+    pub max_changes: Option<u32>, // Cap on vote changes per user; None means unlimited
+    pub visible_to_role: Option<String>, // Only this role (and admins) can see the poll; None means everyone
+    pub is_draft: bool, // New polls stay hidden and can't be activated until published
+    pub window_start: Option<i64>, // Votes are only accepted within [window_start, window_end)
+    pub window_end: Option<i64>,
+    pub is_pinned: bool, // At most one poll is pinned; listings put it first
+    pub quorum: Option<u32>, // Voters needed for the result to count; None means no quorum
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -30,17 +59,181 @@ pub struct PollOption {
     pub option_id: u64,
     pub poll_id: u64,
     pub text: String,
+    pub image_url: Option<String>,
+    pub metadata: Option<String>, // Opaque JSON for clients; not parsed by the module
+    pub added_by: Option<String>, // Voter who added this as a write-in
 }
 
+// Public, so subscribers can see who voted for what on every poll.
+// Poll.anonymous_reports only keeps voters out of the derived reports.
 #[derive(Serialize, Deserialize, Clone, Debug)]
 #[table(name = vote, public)]
 pub struct Vote {
     #[primary_key]
     pub vote_id: u64,
+    #[index(btree)]
     pub poll_id: u64,
     pub user_id: String,
     pub option_id: u64,
     pub voted_at: i64,
+    pub source: Option<String>, // Client channel, one of VOTE_SOURCES
+    pub change_count: u32, // Times the voter switched to a different option
+    pub voided: bool, // Kept for audit but excluded from results
+    pub provisional: bool, // Not counted until the voter calls confirm_vote
+    pub weight: u32, // The voter's vote_weight when the vote was cast
+    pub cast_by: Option<String>, // Proxy who cast it for the voter; None if they cast it
+    pub is_demo: bool, // Synthetic vote from seed_demo_votes
+}
+
+// Votes turned away because the poll wasn't open; see Settings.log_rejected
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[table(name = rejected_vote_attempt)]
+pub struct RejectedVoteAttempt {
+    #[primary_key]
+    #[auto_inc]
+    pub attempt_id: u64,
+    #[index(btree)]
+    pub user_id: String,
+    pub poll_id: u64,
+    pub reason: String,
+    pub attempted_at: i64,
+}
+
+// Append-only log of every vote submission; read through vote_history_for
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[table(name = vote_revision)]
+pub struct VoteRevision {
+    #[primary_key]
+    #[auto_inc]
+    pub revision_id: u64,
+    #[index(btree)]
+    pub poll_id: u64,
+    pub user_id: String,
+    pub option_id: u64,
+    pub changed_at: i64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[table(name = text_response, public)]
+pub struct TextResponse {
+    #[primary_key]
+    pub response_id: u64,
+    #[index(btree)]
+    pub poll_id: u64,
+    pub user_id: String,
+    pub text: String,
+    pub submitted_at: i64,
+    pub flag_count: u32,
+    pub hidden: bool, // Clients should filter hidden responses out
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[table(name = response_flag, public)]
+pub struct ResponseFlag {
+    #[primary_key]
+    #[auto_inc]
+    pub flag_id: u64,
+    #[index(btree)]
+    pub response_id: u64,
+    pub user_id: String,
+}
+
+// Timestamps of a user's recent mutating calls, used by throttle_calls
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[table(name = call_count)]
+pub struct CallCount {
+    #[primary_key]
+    pub user_id: String,
+    pub recent_calls: Vec<i64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[table(name = poll_translation, public)]
+pub struct PollTranslation {
+    #[primary_key]
+    #[auto_inc]
+    pub translation_id: u64,
+    #[index(btree)]
+    pub poll_id: u64,
+    pub lang: String, // Normalized language tag, e.g. "de" or "pt-br"
+    pub question: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[table(name = poll_option_translation, public)]
+pub struct PollOptionTranslation {
+    #[primary_key]
+    #[auto_inc]
+    pub translation_id: u64,
+    #[index(btree)]
+    pub option_id: u64,
+    pub lang: String,
+    pub text: String,
+}
+
+// Grantor has handed their vote to proxy_user_id
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[table(name = proxy, public)]
+pub struct Proxy {
+    #[primary_key]
+    pub grantor_user_id: String,
+    #[index(btree)]
+    pub proxy_user_id: String,
+    pub delegated_at: i64,
+}
+
+// Ranked ballot: option IDs in order of preference. Options left out rank
+// below every listed one.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[table(name = ranked_ballot)]
+pub struct RankedBallot {
+    #[primary_key]
+    #[auto_inc]
+    pub ballot_id: u64,
+    #[index(btree)]
+    pub poll_id: u64,
+    pub user_id: String,
+    pub ranking: Vec<u64>,
+    pub submitted_at: i64,
+}
+
+// Multi-choice ballot: every option the user picks, in ascending option_id
+// order. Kept apart from Vote, which stays one choice per user per poll.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[table(name = choice_ballot)]
+pub struct ChoiceBallot {
+    #[primary_key]
+    #[auto_inc]
+    pub ballot_id: u64,
+    #[index(btree)]
+    pub poll_id: u64,
+    pub user_id: String,
+    pub option_ids: Vec<u64>,
+    pub submitted_at: i64,
+}
+
+// Explicit "no answer" on a poll; private because reasons are tied to users
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[table(name = abstention)]
+pub struct Abstention {
+    #[primary_key]
+    #[auto_inc]
+    pub abstention_id: u64,
+    #[index(btree)]
+    pub poll_id: u64,
+    pub user_id: String,
+    pub reason: Option<String>,
+    pub abstained_at: i64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[table(name = poll_result, public)]
+pub struct PollResult {
+    #[primary_key]
+    pub option_id: u64,
+    #[index(btree)]
+    pub poll_id: u64,
+    pub vote_count: u64,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -49,303 +242,8726 @@ pub struct PresentationState {
     #[primary_key]
     pub id: u8, // Just one row with id=0
     pub current_poll_id: u64,
-    pub state: String, // "waiting", "voting", "results", "ended"
+    pub state: String, // "waiting", "voting", "revealing", "results", "ended", "paused"
+    pub reveal_at: Option<i64>, // While "revealing": when results are shown
 }
 
-// ======== Reducers (Server-side functions) ========
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[table(name = session_state, public)]
+pub struct SessionState {
+    #[primary_key]
+    pub session_id: String,
+    pub current_poll_id: u64,
+    pub state: String, // Same values as PresentationState.state
+}
 
-#[reducer]
-pub fn join_session(ctx: &ReducerContext, session_id: String, role: String) -> Result<(), String> {
-    // Validate role
-    if role != "user" && role != "admin" {
-        return Err("Invalid role. Must be 'user' or 'admin'".to_string());
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[table(name = poll_queue, public)]
+pub struct PollQueue {
+    #[primary_key]
+    #[auto_inc]
+    pub queue_id: u64, // Increases with insertion, so the lowest is the head
+    #[unique]
+    pub poll_id: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[table(name = settings, public)]
+pub struct Settings {
+    #[primary_key]
+    pub id: u8, // Just one row with id=0
+    pub guided_mode: bool, // Only one poll open at a time, driven by activate_poll
+    pub owner_id: String, // Publisher, or the admin who called claim_owner; acts as superadmin
+    pub max_participants: Option<u32>, // Cap on connected non-admin users; None means unlimited
+    pub auto_promote_admin: bool, // When the last admin leaves: promote a user (true) or pause (false)
+    pub poll_cooldown_millis: u64, // Minimum gap between polls created by the same admin
+    pub debug_mode: bool, // Enables developer-only reducers such as set_my_role
+    pub grace_secs: u64, // Votes are still accepted this long after a poll closes
+    pub clear_votes_on_session_switch: bool, // Switching sessions drops the user's votes (true) or is rejected (false)
+    pub locked: bool, // No new users may join; existing ones can still reconnect
+    pub sealed: bool, // Set by seal_session; all further mutations are rejected
+    pub retention_secs: u64, // Closed polls are archived this long after closing
+    pub max_calls_per_window: u32, // Mutating calls allowed per user per CALL_WINDOW_SECS; 0 disables
+    pub max_admins: u32, // Connected admins allowed per session; 0 disables
+    pub log_rejected: bool, // Record votes for closed polls in rejected_vote_attempt
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[table(name = counter, public)]
+pub struct Counter {
+    #[primary_key]
+    pub name: String, // "poll", "poll_option", "vote" or "text_response"
+    pub value: u64, // Last ID handed out
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[table(name = meta, public)]
+pub struct Meta {
+    #[primary_key]
+    pub id: u8, // Just one row with id=0
+    pub schema_version: u32, // Layout the stored data is in; see migrate_if_needed
+    pub migrated_at: i64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[table(name = timeline, public)]
+pub struct Timeline {
+    #[primary_key]
+    #[auto_inc]
+    pub timeline_id: u64,
+    #[index(btree)]
+    pub poll_id: u64,
+    pub bucket: u32, // 0-based index of the time window
+    pub bucket_start: i64,
+    pub option_id: u64,
+    pub vote_count: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[table(name = anomaly_report, public)]
+pub struct AnomalyReport {
+    #[primary_key]
+    #[auto_inc]
+    pub report_id: u64,
+    #[index(btree)]
+    pub poll_id: u64,
+    pub vote_id: u64,
+    pub kind: String, // "missing_option", "duplicate_vote", "unknown_user"
+    pub detail: String,
+    pub found_at: i64,
+}
+
+// Private, since user_ids lists everyone who voted
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[table(name = unique_voter_summary)]
+pub struct UniqueVoterSummary {
+    #[primary_key]
+    pub id: u8, // Just one row with id=0
+    pub voter_count: u64,
+    pub user_ids: Vec<String>,
+    pub computed_at: i64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[table(name = unvoted_poll_list, public)]
+pub struct UnvotedPolls {
+    #[primary_key]
+    pub user_id: String,
+    pub poll_ids: Vec<u64>, // Open polls this user hasn't voted in yet
+    pub computed_at: i64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[table(name = results_summary_text, public)]
+pub struct ResultsSummary {
+    #[primary_key]
+    pub poll_id: u64,
+    pub summary: String,
+    pub winner_option_id: Option<u64>, // None when no votes were cast
+    pub runner_up_option_id: Option<u64>, // None with fewer than two options
+    pub margin: u64, // Winner votes minus runner-up votes; 0 on a tie
+    pub computed_at: i64,
+}
+
+// Ballots of polls with public_ballots go to the public poll_ballot table. An
+// admin listing any other poll gets the private copy, readable only from the
+// owner's connection, so the listing doesn't reach every subscriber.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[table(name = poll_ballot, public)]
+#[table(name = private_poll_ballot)]
+pub struct PollBallot {
+    #[primary_key]
+    #[auto_inc]
+    pub ballot_id: u64,
+    #[index(btree)]
+    pub poll_id: u64,
+    pub user_id: String,
+    pub option_text: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[table(name = platform_count, public)]
+pub struct PlatformCount {
+    #[primary_key]
+    pub platform: String, // "unknown" for clients that didn't report one
+    pub user_count: u64,
+}
+
+// Final results written once by seal_session
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[table(name = sealed_result, public)]
+pub struct SealedResult {
+    #[primary_key]
+    pub option_id: u64,
+    #[index(btree)]
+    pub poll_id: u64,
+    pub question: String,
+    pub option_text: String,
+    pub vote_count: u64,
+    pub sealed_at: i64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[table(name = seat_count, public)]
+pub struct SeatCount {
+    #[primary_key]
+    pub seat: u32,
+    pub user_count: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[table(name = comparison, public, index(name = by_polls, btree(columns = [poll_a, poll_b])))]
+pub struct Comparison {
+    #[primary_key]
+    #[auto_inc]
+    pub comparison_id: u64,
+    pub poll_a: u64,
+    pub poll_b: u64,
+    pub option_text: String,
+    pub percent_a: Option<f64>, // None when the option only exists in poll B
+    pub percent_b: Option<f64>, // None when the option only exists in poll A
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[table(name = participation_row, public)]
+pub struct ParticipationRow {
+    #[primary_key]
+    #[auto_inc]
+    pub row_id: u64,
+    #[index(btree)]
+    pub poll_id: u64,
+    pub option_id: Option<u64>, // None for the "did not vote" row
+    pub label: String,
+    pub count: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[table(name = vote_checksum, public)]
+pub struct VoteChecksum {
+    #[primary_key]
+    pub poll_id: u64,
+    pub checksum: u64, // Hash chain over every vote_checksum_link of the poll
+}
+
+// Append-only log of the changes a poll's checksum chains, in link_id order
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[table(name = vote_checksum_link)]
+pub struct VoteChecksumLink {
+    #[primary_key]
+    #[auto_inc]
+    pub link_id: u64,
+    #[index(btree)]
+    pub poll_id: u64,
+    pub vote_id: u64,
+    pub option_id: u64, // VOTE_REMOVED once the vote was deleted
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[table(name = integrity_report, public)]
+pub struct IntegrityReport {
+    #[primary_key]
+    pub poll_id: u64,
+    pub valid: bool,
+    pub stored_checksum: u64,
+    pub computed_checksum: u64, // Replayed from the links
+    pub rows_match: bool, // Whether the Vote rows are where the links end up
+    pub checked_at: i64,
+}
+
+#[derive(SpacetimeType, Serialize, Deserialize, Clone, Debug)]
+pub struct OptionTally {
+    pub option_id: u64,
+    pub text: String,
+    pub image_url: Option<String>,
+    pub vote_count: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[table(name = current_poll_view, public)]
+pub struct CurrentPollView {
+    #[primary_key]
+    pub user_id: String,
+    pub state: String, // Mirrors presentation_state.state
+    pub poll_id: Option<u64>, // None when no poll is on screen
+    pub question: Option<String>,
+    pub is_active: bool,
+    pub options: Vec<OptionTally>,
+    pub computed_at: i64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[table(name = consensus, public)]
+pub struct Consensus {
+    #[primary_key]
+    pub poll_id: u64,
+    pub total_votes: u64,
+    pub entropy_bits: f64, // Shannon entropy of the vote distribution; 0 with no votes
+    pub agreement: Option<f64>, // 1 = unanimous, 0 = evenly split; None with no votes
+    pub computed_at: i64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[table(name = source_result, public)]
+pub struct SourceResult {
+    #[primary_key]
+    #[auto_inc]
+    pub row_id: u64,
+    #[index(btree)]
+    pub poll_id: u64,
+    pub source: String, // "unknown" for votes without a source
+    pub option_id: u64,
+    pub vote_count: u64,
+}
+
+// Recurring job that archives polls closed longer than the retention window
+#[derive(Clone, Debug)]
+#[table(name = cleanup_schedule, scheduled(cleanup_expired))]
+pub struct CleanupSchedule {
+    #[primary_key]
+    #[auto_inc]
+    pub scheduled_id: u64,
+    pub scheduled_at: ScheduleAt,
+}
+
+// One-shot job that ends a reveal countdown
+#[derive(Clone, Debug)]
+#[table(name = reveal_schedule, scheduled(finish_reveal))]
+pub struct RevealSchedule {
+    #[primary_key]
+    #[auto_inc]
+    pub scheduled_id: u64,
+    pub scheduled_at: ScheduleAt,
+    pub reveal_at: i64, // Matches PresentationState.reveal_at of the countdown it ends
+}
+
+#[derive(Clone, Debug)]
+#[table(name = poll_open_schedule, scheduled(open_scheduled_poll))]
+pub struct PollOpenSchedule {
+    #[primary_key]
+    #[auto_inc]
+    pub scheduled_id: u64,
+    pub scheduled_at: ScheduleAt,
+    #[index(btree)]
+    pub poll_id: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[table(name = duration_bucket, public)]
+pub struct DurationBucket {
+    #[primary_key]
+    pub bucket: u8,
+    pub label: String,
+    pub min_secs: u64,
+    pub user_count: u64,
+}
+
+// Private, because fastest_user_id names a voter and their choice
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[table(name = timing_stat)]
+pub struct TimingStat {
+    #[primary_key]
+    pub poll_id: u64,
+    pub timed_votes: u64, // Votes cast after activation
+    pub median_micros: Option<i64>, // Median time from activation to vote
+    pub fastest_user_id: Option<String>, // Fastest voter for the correct option
+    pub fastest_micros: Option<i64>,
+    pub computed_at: i64,
+}
+
+// Private like vote_revision: it names who voted for what. Read it from the
+// owner's connection (e.g. `spacetime sql`) after calling vote_history_for.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[table(name = vote_history_entry)]
+pub struct VoteHistoryEntry {
+    #[primary_key]
+    #[auto_inc]
+    pub entry_id: u64,
+    #[index(btree)]
+    pub requested_by: String,
+    pub revision_id: u64,
+    pub poll_id: u64,
+    pub user_id: String,
+    pub option_id: u64,
+    pub changed_at: i64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[table(name = answer_matrix_header, public)]
+pub struct AnswerMatrixHeader {
+    #[primary_key]
+    pub id: u8, // Just one row with id=0
+    pub poll_ids: Vec<u64>, // Column order of every AnswerMatrixRow
+    pub computed_at: i64,
+}
+
+// Private: each row is one user's answers across polls
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[table(name = answer_matrix_row)]
+pub struct AnswerMatrixRow {
+    #[primary_key]
+    pub user_id: String,
+    pub option_ids: Vec<Option<u64>>, // None where the user didn't vote
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[table(name = stale_poll, public)]
+pub struct StalePoll {
+    #[primary_key]
+    pub poll_id: u64,
+    pub question: String,
+    pub age_secs: u64,
+    pub vote_count: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[table(name = confidence_interval, public)]
+pub struct ConfidenceInterval {
+    #[primary_key]
+    #[auto_inc]
+    pub row_id: u64,
+    #[index(btree)]
+    pub poll_id: u64,
+    pub option_id: u64,
+    pub proportion: Option<f64>, // None when there are no votes
+    pub lower: Option<f64>,
+    pub upper: Option<f64>,
+    pub reliable: bool, // False below MIN_CONFIDENCE_SAMPLE votes
+}
+
+#[derive(SpacetimeType, Serialize, Deserialize, Clone, Debug)]
+pub struct TemplatePoll {
+    pub question: String,
+    pub options: Vec<OptionInput>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[table(name = poll_template, public)]
+pub struct PollTemplate {
+    #[primary_key]
+    pub name: String,
+    pub polls: Vec<TemplatePoll>, // In presentation order; no votes are kept
+    pub saved_at: i64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[table(name = group_result, public)]
+pub struct GroupResult {
+    #[primary_key]
+    #[auto_inc]
+    pub row_id: u64,
+    #[index(btree)]
+    pub group_id: u64,
+    pub option_text: String,
+    pub vote_count: u64,
+    pub poll_count: u32, // How many polls in the group offered this option
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[table(name = projected_winner_view, public)]
+pub struct ProjectedWinnerView {
+    #[primary_key]
+    pub poll_id: u64,
+    pub leader_option_id: Option<u64>, // None when nobody leads outright
+    pub leader_votes: u64,
+    pub runner_up_votes: u64,
+    pub remaining_voters: u64, // Connected users who haven't voted yet
+    pub contenders: Vec<u64>, // Options that could still finish first
+    pub decided: bool,
+    pub computed_at: i64,
+}
+
+// Private like vote_revision, as rows pair user_id with option_id
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[table(name = export_long_row)]
+pub struct ExportLongRow {
+    #[primary_key]
+    #[auto_inc]
+    pub row_id: u64,
+    #[index(btree)]
+    pub poll_id: u64,
+    pub user_id: Option<String>, // None for anonymous polls
+    pub option_id: u64,
+    pub option_text: String,
+    pub voted_at: i64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[table(name = duplicate_question, public)]
+pub struct DuplicateQuestion {
+    #[primary_key]
+    pub normalized_question: String,
+    pub poll_ids: Vec<u64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[table(name = dead_option, public)]
+pub struct DeadOption {
+    #[primary_key]
+    pub option_id: u64,
+    #[index(btree)]
+    pub poll_id: u64,
+    pub text: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[table(name = results_delta, public)]
+pub struct ResultsDelta {
+    #[primary_key]
+    pub option_id: u64,
+    #[index(btree)]
+    pub poll_id: u64,
+    pub votes_gained: u64, // Votes cast (or changed to this option) after since_micros
+    pub since_micros: i64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[table(name = role_result, public)]
+pub struct RoleResult {
+    #[primary_key]
+    #[auto_inc]
+    pub row_id: u64,
+    #[index(btree)]
+    pub poll_id: u64,
+    pub role: String,
+    pub option_id: u64,
+    pub vote_count: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[table(name = abstention_reason, public)]
+pub struct AbstentionReason {
+    #[primary_key]
+    pub abstention_id: u64,
+    #[index(btree)]
+    pub poll_id: u64,
+    pub reason: String,
+    pub abstained_at: i64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[table(name = visible_poll_list, public)]
+pub struct VisiblePollList {
+    #[primary_key]
+    pub user_id: String,
+    pub poll_ids: Vec<u64>,
+    pub computed_at: i64,
+}
+
+// Private: a streak says which polls a user voted in
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[table(name = participation_streak)]
+pub struct ParticipationStreak {
+    #[primary_key]
+    pub user_id: String,
+    pub longest_streak: u64, // Most consecutive polls voted in, by creation order
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[table(name = similar_poll, public)]
+pub struct SimilarPoll {
+    #[primary_key]
+    #[auto_inc]
+    pub row_id: u64,
+    #[index(btree)]
+    pub poll_id: u64,
+    pub similar_poll_id: u64,
+    pub similarity: f64, // Jaccard index of the normalized option sets, 0..=1
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[table(name = pairwise_preference, public)]
+pub struct PairwisePreference {
+    #[primary_key]
+    #[auto_inc]
+    pub row_id: u64,
+    #[index(btree)]
+    pub poll_id: u64,
+    pub option_id: u64,
+    pub over_option_id: u64,
+    pub ballot_count: u64, // Ballots ranking option_id above over_option_id
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[table(name = condorcet_result, public)]
+pub struct CondorcetResult {
+    #[primary_key]
+    pub poll_id: u64,
+    pub winner_option_id: Option<u64>,
+    pub cycle: bool, // No option beats every other one head-to-head
+    pub ballot_count: u64,
+    pub computed_at: i64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[table(name = vote_concentration_view, public)]
+pub struct VoteConcentration {
+    #[primary_key]
+    pub poll_id: u64,
+    pub gini: f64, // 0 = evenly spread; approaches 1 as votes pile onto one option
+    pub option_count: u64,
+    pub total_votes: u64,
+    pub computed_at: i64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[table(name = effective_options_view, public)]
+pub struct EffectiveOptions {
+    #[primary_key]
+    pub poll_id: u64,
+    pub effective_options: f64, // Inverse Simpson index; 0 when no votes were cast
+    pub option_count: u64,
+    pub total_votes: u64,
+    pub computed_at: i64,
+}
+
+// Written per caller so clients can compare against their own clock
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[table(name = server_time_view, public)]
+pub struct ServerTime {
+    #[primary_key]
+    pub user_id: String,
+    pub server_micros: i64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[table(name = cohort_participation_row, public)]
+pub struct CohortParticipation {
+    #[primary_key]
+    #[auto_inc]
+    pub row_id: u64,
+    #[index(btree)]
+    pub poll_id: u64,
+    pub cohort: String, // See cohort_participation
+    pub users: u64,
+    pub voters: u64,
+    pub turnout_percent: f64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[table(name = turnout_point, public)]
+pub struct TurnoutPoint {
+    #[primary_key]
+    #[auto_inc]
+    pub point_id: u64,
+    #[index(btree)]
+    pub poll_id: u64,
+    pub bucket: u32,
+    pub bucket_end: i64,
+    pub cumulative_votes: u64,
+    pub turnout_percent: f64, // Share of the audience that had voted by bucket_end
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[table(name = pacing_stats_view, public)]
+pub struct PacingStats {
+    #[primary_key]
+    pub id: u8, // Just one row with id=0
+    pub activation_count: u64,
+    pub average_gap_micros: Option<i64>, // None with fewer than two activations
+    pub max_gap_micros: Option<i64>,
+    pub computed_at: i64,
+}
+
+// Aggregates only, never identities or individual ballots, so safe for every client
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[table(name = public_export_row, public)]
+pub struct PublicExportRow {
+    #[primary_key]
+    pub option_id: u64,
+    #[index(btree)]
+    pub poll_id: u64,
+    pub question: String,
+    pub option_text: String,
+    pub vote_count: u64,
+    pub percent: f64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[table(name = quorum_status_view, public)]
+pub struct QuorumStatus {
+    #[primary_key]
+    pub poll_id: u64,
+    pub quorum: Option<u32>,
+    pub voters: u64,
+    pub met: bool, // Always true for polls without a quorum
+    pub computed_at: i64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[table(name = health_view, public)]
+pub struct Health {
+    #[primary_key]
+    pub id: u8, // Just one row with id=0
+    pub state: String, // presentation_state.state, or "uninitialized"
+    pub current_poll_id: u64,
+    pub total_users: u64,
+    pub connected_users: u64,
+    pub total_votes: u64,
+    pub schema_version: u32,
+    pub sealed: bool,
+    pub checked_at: i64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[table(name = raffle, public)]
+pub struct Raffle {
+    #[primary_key]
+    pub poll_id: u64,
+    pub winner_user_id: String,
+    pub entrant_count: u64,
+    pub seed: u64, // Hash of drawn_at and the counted votes; rerunning with both gives the same winner
+    pub drawn_at: i64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[table(name = co_selection_pair, public)]
+pub struct CoSelectionPair {
+    #[primary_key]
+    #[auto_inc]
+    pub row_id: u64,
+    #[index(btree)]
+    pub poll_id: u64,
+    pub option_a: u64, // option_a < option_b
+    pub option_b: u64,
+    pub user_count: u64, // Users who selected both options
+}
+
+#[derive(SpacetimeType, Serialize, Deserialize, Clone, Debug)]
+pub struct LocalizedOption {
+    pub option_id: u64,
+    pub text: String,
+    pub translated: bool, // False when falling back to the original text
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[table(name = localized_poll_view, public)]
+pub struct LocalizedPoll {
+    #[primary_key]
+    pub user_id: String,
+    pub poll_id: u64,
+    pub lang: String,
+    pub question: String,
+    pub question_translated: bool,
+    pub options: Vec<LocalizedOption>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[table(name = results_report, public)]
+pub struct Report {
+    #[primary_key]
+    pub id: u8, // Just one row with id=0
+    pub text: String, // Plain-text handout, one block per closed poll
+    pub poll_count: u32,
+    pub generated_at: i64,
+}
+
+// ======== Limits ========
+
+// Bumped whenever tables change shape
+const SCHEMA_VERSION: u32 = 1;
+
+const MAX_QUESTION_LEN: usize = 500;
+const MAX_OPTION_LEN: usize = 200;
+const MAX_OPTIONS_PER_POLL: usize = 100;
+const MAX_TIMELINE_BUCKETS: i64 = 120;
+const MAX_CLOSE_REASON_LEN: usize = 200;
+const MAX_SESSION_ID_LEN: usize = 64;
+const MAX_IMAGE_URL_LEN: usize = 2048;
+const MAX_METADATA_LEN: usize = 4096;
+const MAX_WRITE_IN_LEN: usize = 100;
+const MAX_WRITE_INS_PER_USER: usize = 3;
+const MAX_DEMO_VOTES: u64 = 10_000;
+const MAX_REVEAL_SECS: u32 = 600;
+const VOTE_SOURCES: [&str; 4] = ["web", "mobile", "kiosk", "sms"];
+const PLATFORMS: [&str; 4] = ["web", "ios", "android", "kiosk"];
+const MAX_TEXT_RESPONSE_LEN: usize = 500;
+const MAX_ABSTAIN_REASON_LEN: usize = 200;
+const MAX_MATRIX_CELLS: usize = 50_000;
+const MAX_TEMPLATE_NAME_LEN: usize = 64;
+const MAX_SIMILAR_POLLS: usize = 5;
+const CONFIDENCE_Z: f64 = 1.96; // 95% confidence
+const MIN_CONFIDENCE_SAMPLE: u64 = 10;
+const FLAG_HIDE_THRESHOLD: u32 = 3;
+const CLEANUP_INTERVAL_SECS: u64 = 60 * 60;
+const CALL_WINDOW_SECS: i64 = 60;
+// Tables whose IDs come from the counter table (see next_id)
+const COUNTER_NAMES: [&str; 4] = ["poll", "poll_option", "vote", "text_response"];
+// Lower bounds (in seconds) and labels of the connection duration histogram
+const DURATION_BUCKETS: [(u64, &str); 6] = [
+    (0, "under 1 min"),
+    (60, "1-5 min"),
+    (300, "5-15 min"),
+    (900, "15-30 min"),
+    (1800, "30-60 min"),
+    (3600, "over 1 hour"),
+];
+
+// ======== Error Codes ========
+
+// Every reducer error reads "<CODE>: <message>". Clients branch on the code,
+// which is stable; the message is for humans and may change.
+const E_NOT_FOUND: &str = "E_NOT_FOUND";
+const E_FORBIDDEN: &str = "E_FORBIDDEN"; // Caller lacks the role or permission
+const E_INVALID_INPUT: &str = "E_INVALID_INPUT";
+const E_CLOSED: &str = "E_CLOSED"; // Voting or joining isn't open right now
+const E_CONFLICT: &str = "E_CONFLICT"; // Request clashes with the current state
+const E_RATE_LIMITED: &str = "E_RATE_LIMITED";
+const E_SEALED: &str = "E_SEALED";
+
+fn fail(code: &str, message: impl std::fmt::Display) -> String {
+    format!("{}: {}", code, message)
+}
+
+// ======== Helpers ========
+
+fn default_settings() -> Settings {
+    Settings {
+        id: 0,
+        guided_mode: true,
+        owner_id: String::new(),
+        max_participants: None,
+        auto_promote_admin: false,
+        poll_cooldown_millis: 2_000,
+        debug_mode: false,
+        grace_secs: 0,
+        clear_votes_on_session_switch: false,
+        locked: false,
+        sealed: false,
+        retention_secs: 30 * 24 * 60 * 60,
+        max_calls_per_window: 120,
+        max_admins: 5,
+        log_rejected: false,
+    }
+}
+
+// Settings row, falling back to defaults for databases created before it existed
+fn get_settings(ctx: &ReducerContext) -> Settings {
+    ctx.db.settings().id().find(0).unwrap_or_else(default_settings)
+}
+
+fn save_settings(ctx: &ReducerContext, settings: Settings) {
+    if ctx.db.settings().id().find(0).is_some() {
+        ctx.db.settings().id().update(settings);
+    } else {
+        ctx.db.settings().insert(settings);
+    }
+}
+
+// Returns the calling user if they are an admin, otherwise `error`
+fn require_admin(ctx: &ReducerContext, error: &str) -> Result<User, String> {
+    match ctx.db.user().user_id().find(ctx.sender.to_string()) {
+        Some(user) if user.role == "admin" => Ok(user),
+        Some(_) => Err(fail(E_FORBIDDEN, error)),
+        None => Err(fail(E_NOT_FOUND, "User not found")),
+    }
+}
+
+fn require_superadmin(ctx: &ReducerContext) -> Result<(), String> {
+    let owner_id = get_settings(ctx).owner_id;
+    if owner_id.is_empty() || owner_id != ctx.sender.to_string() {
+        return Err(fail(E_FORBIDDEN, "Only the superadmin can do this"));
+    }
+    Ok(())
+}
+
+// Guard for every reducer that changes polls, votes, users or settings
+fn guard_mutation(ctx: &ReducerContext) -> Result<(), String> {
+    check_not_sealed(&get_settings(ctx))?;
+    throttle_calls(ctx)
+}
+
+fn check_not_sealed(settings: &Settings) -> Result<(), String> {
+    if settings.sealed {
+        return Err(fail(E_SEALED, "Session is sealed"));
+    }
+    Ok(())
+}
+
+// The caller's calls inside the window, with this one added, or an error
+// once `limit` calls already fall inside it
+fn recorded_call(existing: Option<&CallCount>, now: i64, limit: u32) -> Result<Vec<i64>, String> {
+    let window_start = now - CALL_WINDOW_SECS * 1_000_000;
+    let mut recent_calls = existing
+        .map(|c| c.recent_calls.iter().copied().filter(|&t| t > window_start).collect::<Vec<_>>())
+        .unwrap_or_default();
+    if recent_calls.len() >= limit as usize {
+        return Err(fail(E_RATE_LIMITED, "Too many requests"));
+    }
+    recent_calls.push(now);
+    Ok(recent_calls)
+}
+
+// Rolling-window limit on mutating calls per identity. Calls that fail roll
+// back their own bookkeeping, so only calls that succeed are counted.
+fn throttle_calls(ctx: &ReducerContext) -> Result<(), String> {
+    let limit = get_settings(ctx).max_calls_per_window;
+    if limit == 0 {
+        return Ok(());
+    }
+    
+    let user_id = ctx.sender.to_string();
+    let call_table = ctx.db.call_count();
+    let existing = call_table.user_id().find(&user_id);
+    let recent_calls = recorded_call(existing.as_ref(), ctx.timestamp.to_micros_since_unix_epoch(), limit)?;
+    let row = CallCount { user_id, recent_calls };
+    if existing.is_some() {
+        call_table.user_id().update(row);
+    } else {
+        call_table.insert(row);
+    }
+    Ok(())
+}
+
+// Databases created before the cleanup job existed get it on first use
+fn ensure_cleanup_scheduled(ctx: &ReducerContext) {
+    let schedule_table = ctx.db.cleanup_schedule();
+    if schedule_table.iter().next().is_none() {
+        schedule_table.insert(CleanupSchedule {
+            scheduled_id: 0,
+            scheduled_at: ScheduleAt::Interval(Duration::from_secs(CLEANUP_INTERVAL_SECS).into()),
+        });
+    }
+}
+
+// Highest ID currently stored for a counter's table
+fn max_id(ctx: &ReducerContext, name: &str) -> u64 {
+    match name {
+        "poll" => ctx.db.poll().iter().map(|p| p.poll_id).max(),
+        "poll_option" => ctx.db.poll_option().iter().map(|o| o.option_id).max(),
+        "vote" => ctx.db.vote().iter().map(|v| v.vote_id).max(),
+        "text_response" => ctx.db.text_response().iter().map(|r| r.response_id).max(),
+        _ => None,
+    }
+    .unwrap_or(0)
+}
+
+fn set_counter(ctx: &ReducerContext, name: &str, value: u64) {
+    let counter = Counter {
+        name: name.to_string(),
+        value,
+    };
+    if ctx.db.counter().name().find(name.to_string()).is_some() {
+        ctx.db.counter().name().update(counter);
+    } else {
+        ctx.db.counter().insert(counter);
+    }
+}
+
+// Allocates the next ID for a table. A missing counter is seeded from the
+// table's current max so upgraded databases don't reuse existing IDs.
+fn next_id(ctx: &ReducerContext, name: &str) -> u64 {
+    reserve_ids(ctx, name, 1)
+}
+
+// Allocates a contiguous block of IDs with a single counter write and
+// returns the first one
+fn reserve_ids(ctx: &ReducerContext, name: &str, count: u64) -> u64 {
+    let current = match ctx.db.counter().name().find(name.to_string()) {
+        Some(counter) => counter.value,
+        None => max_id(ctx, name),
+    };
+    set_counter(ctx, name, current + count);
+    current + 1
+}
+
+// Closes an open poll, recording when and why
+fn deactivate_poll(ctx: &ReducerContext, poll: Poll, reason: Option<String>) -> Poll {
+    let updated_poll = closed_poll(poll, ctx.timestamp.to_micros_since_unix_epoch(), reason);
+    ctx.db.poll().poll_id().update(updated_poll.clone());
+    updated_poll
+}
+
+fn closed_poll(poll: Poll, closed_at: i64, reason: Option<String>) -> Poll {
+    Poll {
+        is_active: false,
+        closed_at: Some(closed_at),
+        close_reason: reason,
+        ..poll
+    }
+}
+
+// Session IDs are lowercased and limited to [a-z0-9_-] so they can't be used
+// to spread state over arbitrary keys
+fn normalize_session_id(session_id: &str) -> Result<String, String> {
+    let session_id = session_id.trim().to_lowercase();
+    let valid = !session_id.is_empty()
+        && session_id.len() <= MAX_SESSION_ID_LEN
+        && session_id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        return Err(fail(E_INVALID_INPUT, "Invalid session id"));
+    }
+    Ok(session_id)
+}
+
+// Accepts a primary language subtag of 2-3 letters with an optional region
+// or script subtag ("en", "pt-BR", "zh-Hant"); stored lowercased
+fn normalize_lang(lang: &str) -> Result<String, String> {
+    let lang = lang.trim().to_lowercase();
+    let mut parts = lang.split('-');
+    let primary = parts.next().unwrap_or_default();
+    let subtag = parts.next();
+    let valid = (2..=3).contains(&primary.len())
+        && primary.chars().all(|c| c.is_ascii_lowercase())
+        && subtag.is_none_or(|t| (2..=4).contains(&t.len()) && t.chars().all(|c| c.is_ascii_alphanumeric()))
+        && parts.next().is_none();
+    if !valid {
+        return Err(fail(E_INVALID_INPUT, "Invalid language code"));
+    }
+    Ok(lang)
+}
+
+// Trims a close reason and checks its length; blank reasons become None
+fn validate_close_reason(reason: Option<String>) -> Result<Option<String>, String> {
+    let reason = reason.map(|r| r.trim().to_string()).filter(|r| !r.is_empty());
+    if let Some(r) = &reason {
+        if r.chars().count() > MAX_CLOSE_REASON_LEN {
+            return Err(fail(E_INVALID_INPUT, format!("Close reason must be at most {} characters", MAX_CLOSE_REASON_LEN)));
+        }
+    }
+    Ok(reason)
+}
+
+// Trims an abstention reason and checks its length; blank reasons become None
+fn validate_abstain_reason(reason: Option<String>) -> Result<Option<String>, String> {
+    let reason = reason
+        .map(|r| r.trim().to_string())
+        .filter(|r| !r.is_empty());
+    if reason.as_ref().is_some_and(|r| r.chars().count() > MAX_ABSTAIN_REASON_LEN) {
+        return Err(fail(E_INVALID_INPUT, format!("Reason exceeds the maximum length of {} characters", MAX_ABSTAIN_REASON_LEN)));
+    }
+    Ok(reason)
+}
+
+// Control characters (including newlines and tabs) are rejected rather than
+// stripped, so what gets stored is exactly what the admin typed. Other
+// Unicode such as emoji and accents is fine.
+fn validate_plain_text(text: &str) -> Result<(), String> {
+    if text.chars().any(char::is_control) {
+        return Err(fail(E_INVALID_INPUT, "Text contains invalid characters"));
+    }
+    Ok(())
+}
+
+fn validate_question(question: &str) -> Result<(), String> {
+    if question.chars().count() > MAX_QUESTION_LEN {
+        return Err(fail(E_INVALID_INPUT, format!("Question exceeds the maximum length of {} characters", MAX_QUESTION_LEN)));
+    }
+    validate_plain_text(question)
+}
+
+fn validate_option_text(text: &str) -> Result<(), String> {
+    if text.chars().count() > MAX_OPTION_LEN {
+        return Err(fail(E_INVALID_INPUT, format!("Option text exceeds the maximum length of {} characters", MAX_OPTION_LEN)));
+    }
+    validate_plain_text(text)
+}
+
+// Checks a whole batch of options up front, so nothing is inserted unless
+// every option is valid
+fn validate_options(options: &[OptionInput]) -> Result<(), String> {
+    if options.len() > MAX_OPTIONS_PER_POLL {
+        return Err(fail(E_INVALID_INPUT, format!("A poll can have at most {} options", MAX_OPTIONS_PER_POLL)));
+    }
+    for option in options {
+        validate_option_text(&option.text)?;
+        if let Some(url) = &option.image_url {
+            if url.len() > MAX_IMAGE_URL_LEN {
+                return Err(fail(E_INVALID_INPUT, format!("Image URL must be at most {} characters", MAX_IMAGE_URL_LEN)));
+            }
+            if !url.starts_with("http://") && !url.starts_with("https://") {
+                return Err(fail(E_INVALID_INPUT, "Image URL must start with http:// or https://"));
+            }
+        }
+        if option.metadata.as_ref().is_some_and(|m| m.len() > MAX_METADATA_LEN) {
+            return Err(fail(E_INVALID_INPUT, format!("Option metadata must be at most {} bytes", MAX_METADATA_LEN)));
+        }
+    }
+    Ok(())
+}
+
+// Looks up an option and checks it belongs to the given poll. An option from
+// another poll usually means the client sent the wrong poll_id.
+fn resolve_option(ctx: &ReducerContext, poll_id: u64, option_id: u64) -> Result<PollOption, String> {
+    option_for_poll(ctx.db.poll_option().option_id().find(option_id), poll_id)
+}
+
+// Tells a wrong poll_id apart from an option that doesn't exist at all
+fn option_for_poll(found: Option<PollOption>, poll_id: u64) -> Result<PollOption, String> {
+    match found {
+        Some(option) if option.poll_id != poll_id => Err(fail(E_INVALID_INPUT, "Option belongs to a different poll")),
+        Some(option) => Ok(option),
+        None => Err(fail(E_NOT_FOUND, "Option not found for this poll")),
+    }
+}
+
+// Inserts an option with an empty result row
+fn insert_option(ctx: &ReducerContext, poll_id: u64, option_input: OptionInput) -> PollOption {
+    let option_id = next_id(ctx, "poll_option");
+    insert_option_with_id(ctx, poll_id, option_id, option_input)
+}
+
+fn insert_option_with_id(ctx: &ReducerContext, poll_id: u64, option_id: u64, option_input: OptionInput) -> PollOption {
+    let option = PollOption {
+        option_id,
+        poll_id,
+        text: option_input.text,
+        image_url: option_input.image_url,
+        metadata: option_input.metadata,
+        added_by: None,
+    };
+    // Every option starts with an empty result row
+    ctx.db.poll_result().insert(PollResult {
+        option_id: option.option_id,
+        poll_id,
+        vote_count: 0,
+    });
+    ctx.db.poll_option().insert(option)
+}
+
+// Rejects a poll created within the cooldown of the admin's previous one,
+// which is almost always a double-submitted form
+fn check_poll_cooldown(user: &User, now: i64, cooldown_millis: u64) -> Result<(), String> {
+    let cooldown_micros = cooldown_millis as i64 * 1_000;
+    if let Some(last) = user.last_created_poll_at {
+        if now - last < cooldown_micros {
+            return Err(fail(E_RATE_LIMITED, "Please wait before creating another poll"));
+        }
+    }
+    Ok(())
+}
+
+fn enforce_poll_cooldown(ctx: &ReducerContext, user: User) -> Result<(), String> {
+    let now = ctx.timestamp.to_micros_since_unix_epoch();
+    check_poll_cooldown(&user, now, get_settings(ctx).poll_cooldown_millis)?;
+    
+    ctx.db.user().user_id().update(User {
+        last_created_poll_at: Some(now),
+        ..user
+    });
+    Ok(())
+}
+
+fn validate_template_name(name: &str) -> Result<(), String> {
+    let valid_chars = name.chars().all(|c| c.is_alphanumeric() || c == ' ' || c == '-' || c == '_');
+    if name.trim().is_empty() || name.chars().count() > MAX_TEMPLATE_NAME_LEN || !valid_chars {
+        return Err(fail(E_INVALID_INPUT, format!(
+            "Template name must be 1-{} letters, digits, spaces, dashes or underscores",
+            MAX_TEMPLATE_NAME_LEN
+        )));
+    }
+    Ok(())
+}
+
+// Inserts a new, inactive poll with its options and empty results
+fn insert_poll(ctx: &ReducerContext, question: String, options: Vec<OptionInput>) -> u64 {
+    // Generate poll ID
+    let poll_table = ctx.db.poll();
+    let poll_id = next_id(ctx, "poll");
+    
+    // Create poll
+    let poll = Poll {
+        poll_id,
+        question,
+        is_active: false, // Not active until explicitly activated
+        created_at: ctx.timestamp.to_micros_since_unix_epoch(),
+        activated_at: None,
+        anonymous_reports: false,
+        archived: false,
+        allow_change: true,
+        public_ballots: false,
+        closed_at: None,
+        close_reason: None,
+        allow_write_ins: false,
+        scheduled_open_at: None,
+        correct_option_id: None,
+        max_votes: None,
+        created_by: Some(ctx.sender.to_string()),
+        creator_can_vote: true,
+        group_id: None,
+        requires_poll_id: None,
+        max_changes: None,
+        visible_to_role: None,
+        is_draft: true,
+        window_start: None,
+        window_end: None,
+        is_pinned: false,
+        quorum: None,
+    };
+    
+    poll_table.insert(poll);
+    
+    // Create options, reserving all their IDs at once
+    let first_option_id = reserve_ids(ctx, "poll_option", options.len() as u64);
+    for (option_id, option_input) in (first_option_id..).zip(options) {
+        insert_option_with_id(ctx, poll_id, option_id, option_input);
+    }
+    
+    poll_id
+}
+
+// Resets presentation and session state that points at a poll which no longer
+// exists. Returns how many rows were repaired.
+fn heal_presentation_state(ctx: &ReducerContext) -> u64 {
+    // A sealed session's state is final, even if it looks stale
+    if get_settings(ctx).sealed {
+        return 0;
+    }
+    let poll_table = ctx.db.poll();
+    let poll_exists = |poll_id| poll_table.poll_id().find(poll_id).is_some();
+    let mut healed = 0;
+    
+    let presentation_table = ctx.db.presentation_state();
+    if let Some(state) = presentation_table.id().find(0).and_then(|s| healed_presentation(s, poll_exists)) {
+        presentation_table.id().update(state);
+        healed += 1;
+    }
+    
+    let session_table = ctx.db.session_state();
+    let stale = session_table.iter()
+        .filter_map(|s| healed_session(s, poll_exists))
+        .collect::<Vec<_>>();
+    for session in stale {
+        session_table.session_id().update(session);
+        healed += 1;
+    }
+    
+    healed
+}
+
+// The state reset to "waiting" if it points at a poll that no longer exists
+fn healed_presentation(state: PresentationState, poll_exists: impl Fn(u64) -> bool) -> Option<PresentationState> {
+    if state.current_poll_id == 0 || poll_exists(state.current_poll_id) {
+        return None;
+    }
+    Some(PresentationState {
+        current_poll_id: 0,
+        state: "waiting".to_string(),
+        reveal_at: None,
+        ..state
+    })
+}
+
+fn healed_session(session: SessionState, poll_exists: impl Fn(u64) -> bool) -> Option<SessionState> {
+    if session.current_poll_id == 0 || poll_exists(session.current_poll_id) {
+        return None;
+    }
+    Some(SessionState {
+        current_poll_id: 0,
+        state: "waiting".to_string(),
+        ..session
+    })
+}
+
+// Admins always see every poll so they can manage restricted ones and drafts
+fn poll_visible_to(poll: &Poll, user: &User) -> bool {
+    user.role == "admin"
+        || (!poll.is_draft && poll.visible_to_role.as_ref().is_none_or(|role| *role == user.role))
+}
+
+// A prerequisite counts as completed once it has been activated and closed again
+fn check_prerequisite(ctx: &ReducerContext, poll: &Poll) -> Result<(), String> {
+    prerequisite_met(poll, |poll_id| ctx.db.poll().poll_id().find(poll_id))
+}
+
+fn prerequisite_met(poll: &Poll, find_poll: impl Fn(u64) -> Option<Poll>) -> Result<(), String> {
+    if let Some(required_id) = poll.requires_poll_id {
+        let completed = find_poll(required_id)
+            .is_some_and(|p| p.activated_at.is_some() && !p.is_active);
+        if !completed {
+            return Err(fail(E_CONFLICT, "Prerequisite poll not completed"));
+        }
+    }
+    Ok(())
+}
+
+// Opens a poll and puts it on the presentation screen
+fn present_poll(ctx: &ReducerContext, poll: Poll) {
+    let poll_table = ctx.db.poll();
+    let poll_id = poll.poll_id;
+    
+    // In guided mode only one poll is open at a time, so close the others
+    let guided = get_settings(ctx).guided_mode;
+    for p in poll_table.iter() {
+        if closes_on_present(&p, poll_id, guided) {
+            deactivate_poll(ctx, p, Some("auto-closed".to_string()));
+        }
+    }
+    
+    // Activate this poll by updating. Opening it by hand supersedes any
+    // pending scheduled open.
+    ctx.db.poll_open_schedule().poll_id().delete(poll_id);
+    let updated_poll = Poll {
+        is_active: true,
+        activated_at: Some(ctx.timestamp.to_micros_since_unix_epoch()),
+        closed_at: None,
+        close_reason: None,
+        scheduled_open_at: None,
+        ..poll
+    };
+    
+    poll_table.poll_id().update(updated_poll);
+    
+    // Update presentation state
+    let presentation_table = ctx.db.presentation_state();
+    if presentation_table.id().find(0).is_some() {
+        // Update presentation state
+        let new_state = PresentationState {
+            id: 0,
+            current_poll_id: poll_id,
+            state: "voting".to_string(),
+            reveal_at: None,
+        };
+        
+        presentation_table.id().update(new_state);
+    }
+}
+
+// Outside guided mode any number of polls stay open side by side
+fn closes_on_present(other: &Poll, presented_poll_id: u64, guided: bool) -> bool {
+    guided && other.is_active && other.poll_id != presented_poll_id
+}
+
+// Applies a vote delta to a single option's result row, so subscribers
+// only receive the row that changed
+fn adjust_result(ctx: &ReducerContext, poll_id: u64, option_id: u64, delta: i64) {
+    let result_table = ctx.db.poll_result();
+    match result_table.option_id().find(option_id) {
+        Some(result) => {
+            let vote_count = result.vote_count.saturating_add_signed(delta);
+            result_table.option_id().update(PollResult { vote_count, ..result });
+        }
+        None => {
+            result_table.insert(PollResult {
+                option_id,
+                poll_id,
+                vote_count: 0u64.saturating_add_signed(delta),
+            });
+        }
+    }
+}
+
+// Rebuilds a poll's result rows from the Vote table
+fn recount_results(ctx: &ReducerContext, poll_id: u64) {
+    let result_table = ctx.db.poll_result();
+    result_table.poll_id().delete(poll_id);
+    
+    let votes = ctx.db.vote().iter()
+        .filter(|v| v.poll_id == poll_id)
+        .collect::<Vec<_>>();
+    let options = ctx.db.poll_option().iter()
+        .filter(|o| o.poll_id == poll_id)
+        .collect::<Vec<_>>();
+    for result in tally_results(&options, &votes) {
+        result_table.insert(result);
+    }
+}
+
+// One result row per option, counted from the votes that count
+fn tally_results(options: &[PollOption], votes: &[Vote]) -> Vec<PollResult> {
+    options.iter()
+        .map(|option| PollResult {
+            option_id: option.option_id,
+            poll_id: option.poll_id,
+            vote_count: votes.iter()
+                .filter(|v| v.option_id == option.option_id && !v.voided && !v.provisional)
+                .map(|v| v.weight as u64)
+                .sum(),
+        })
+        .collect()
+}
+
+// Option ID chained when a vote is deleted; real option IDs start at 1
+const VOTE_REMOVED: u64 = 0;
+
+// One link of the vote hash chain (FNV-1a, so it is stable across builds)
+fn chain_checksum(previous: u64, vote_id: u64, option_id: u64) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in previous.to_le_bytes().iter()
+        .chain(vote_id.to_le_bytes().iter())
+        .chain(option_id.to_le_bytes().iter())
+    {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+// Replays links in order: the checksum they chain to, and the (vote_id,
+// option_id) pairs they leave behind, sorted by vote_id
+fn replay_links(mut links: Vec<VoteChecksumLink>) -> (u64, Vec<(u64, u64)>) {
+    links.sort_by_key(|l| l.link_id);
+    let mut checksum = 0;
+    let mut state: Vec<(u64, u64)> = Vec::new();
+    for link in links {
+        checksum = chain_checksum(checksum, link.vote_id, link.option_id);
+        state.retain(|(vote_id, _)| *vote_id != link.vote_id);
+        if link.option_id != VOTE_REMOVED {
+            state.push((link.vote_id, link.option_id));
+        }
+    }
+    state.sort();
+    (checksum, state)
+}
+
+// Compares the stored checksum with the links, and the links with the Vote
+// rows. Editing a row outside the reducers breaks the second check, and
+// editing the links as well breaks the first.
+fn integrity_report(poll_id: u64, stored_checksum: u64, links: Vec<VoteChecksumLink>, votes: &[Vote], checked_at: i64) -> IntegrityReport {
+    let (computed_checksum, state) = replay_links(links);
+    let mut rows = votes.iter().map(|v| (v.vote_id, v.option_id)).collect::<Vec<_>>();
+    rows.sort();
+    let rows_match = rows == state;
+    IntegrityReport {
+        poll_id,
+        valid: rows_match && stored_checksum == computed_checksum,
+        stored_checksum,
+        computed_checksum,
+        rows_match,
+        checked_at,
+    }
+}
+
+// Verifies a poll and records the outcome in integrity_report
+fn check_integrity(ctx: &ReducerContext, poll_id: u64) -> IntegrityReport {
+    let stored_checksum = ctx.db.vote_checksum().poll_id().find(poll_id).map_or(0, |c| c.checksum);
+    let links = ctx.db.vote_checksum_link().poll_id().filter(poll_id).collect::<Vec<_>>();
+    let votes = ctx.db.vote().poll_id().filter(poll_id).collect::<Vec<_>>();
+    let report = integrity_report(poll_id, stored_checksum, links, &votes, ctx.timestamp.to_micros_since_unix_epoch());
+    
+    let report_table = ctx.db.integrity_report();
+    if report_table.poll_id().find(poll_id).is_some() {
+        report_table.poll_id().update(report.clone());
+    } else {
+        report_table.insert(report.clone());
+    }
+    report
+}
+
+// Links `votes` in vote_id order as the start of a poll's chain and returns
+// the checksum. This accepts the rows as they are, so it is only for polls
+// without a chain yet.
+fn start_chain(ctx: &ReducerContext, poll_id: u64, mut votes: Vec<Vote>) -> u64 {
+    votes.sort_by_key(|v| v.vote_id);
+    let link_table = ctx.db.vote_checksum_link();
+    votes.iter().fold(0, |checksum, v| {
+        link_table.insert(VoteChecksumLink { link_id: 0, poll_id, vote_id: v.vote_id, option_id: v.option_id });
+        chain_checksum(checksum, v.vote_id, v.option_id)
+    })
+}
+
+// Chains one change onto the poll's checksum. The chain is only ever extended,
+// so a later change can't fold an out-of-band edit back in. A poll that had
+// votes before checksums existed starts from its other votes.
+fn extend_checksum(ctx: &ReducerContext, poll_id: u64, vote_id: u64, option_id: u64) {
+    let link_table = ctx.db.vote_checksum_link();
+    let previous = match ctx.db.vote_checksum().poll_id().find(poll_id) {
+        Some(row) => row.checksum,
+        None if link_table.poll_id().filter(poll_id).next().is_none() => {
+            let earlier = ctx.db.vote().poll_id().filter(poll_id)
+                .filter(|v| v.vote_id != vote_id)
+                .collect::<Vec<_>>();
+            start_chain(ctx, poll_id, earlier)
+        }
+        None => 0,
+    };
+    link_table.insert(VoteChecksumLink { link_id: 0, poll_id, vote_id, option_id });
+    store_checksum(ctx, poll_id, chain_checksum(previous, vote_id, option_id));
+}
+
+fn store_checksum(ctx: &ReducerContext, poll_id: u64, checksum: u64) {
+    let checksum_table = ctx.db.vote_checksum();
+    let row = VoteChecksum { poll_id, checksum };
+    if checksum_table.poll_id().find(poll_id).is_some() {
+        checksum_table.poll_id().update(row);
+    } else {
+        checksum_table.insert(row);
+    }
+}
+
+// The (poll, option) result rows that lose votes when `votes` are deleted,
+// and by how much
+fn vote_removals(votes: &[Vote]) -> Vec<(u64, u64, i64)> {
+    votes.iter()
+        .filter(|v| !v.voided && !v.provisional)
+        .map(|v| (v.poll_id, v.option_id, -(v.weight as i64)))
+        .collect()
+}
+
+// Deletes every vote cast by a user, keeping results and checksums in step.
+// Returns how many votes were removed.
+fn clear_user_votes(ctx: &ReducerContext, user_id: &str) -> u64 {
+    let vote_table = ctx.db.vote();
+    let votes = vote_table.iter()
+        .filter(|v| v.user_id == user_id)
+        .collect::<Vec<_>>();
+    
+    for vote in &votes {
+        vote_table.vote_id().delete(vote.vote_id);
+        extend_checksum(ctx, vote.poll_id, vote.vote_id, VOTE_REMOVED);
+    }
+    for (poll_id, option_id, delta) in vote_removals(&votes) {
+        adjust_result(ctx, poll_id, option_id, delta);
+    }
+    
+    votes.len() as u64
+}
+
+// Vote count for each of a poll's options, in option order
+fn option_counts(ctx: &ReducerContext, poll_id: u64) -> Vec<(PollOption, u64)> {
+    let result_table = ctx.db.poll_result();
+    let mut options = ctx.db.poll_option().iter()
+        .filter(|o| o.poll_id == poll_id)
+        .collect::<Vec<_>>();
+    options.sort_by_key(|o| o.option_id);
+    
+    options.into_iter()
+        .map(|o| {
+            let count = result_table.option_id().find(o.option_id)
+                .map_or(0, |r| r.vote_count);
+            (o, count)
+        })
+        .collect()
+}
+
+fn percent(count: u64, total: u64) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        count as f64 * 100.0 / total as f64
+    }
+}
+
+// Case- and whitespace-insensitive form used to match texts
+fn normalize_text(text: &str) -> String {
+    text.trim().to_lowercase()
+}
+
+fn plural(count: u64, word: &str) -> String {
+    if count == 1 {
+        format!("{} {}", count, word)
+    } else {
+        format!("{} {}s", count, word)
+    }
+}
+
+// Fails if one more connected admin in session_id would exceed Settings.max_admins
+fn check_admin_limit(ctx: &ReducerContext, session_id: &str) -> Result<(), String> {
+    check_admin_cap(get_settings(ctx).max_admins, ctx.db.user().iter(), session_id)
+}
+
+// A max of 0 means no limit
+fn check_admin_cap(max: u32, users: impl Iterator<Item = User>, session_id: &str) -> Result<(), String> {
+    if max == 0 {
+        return Ok(());
+    }
+    let admins = users
+        .filter(|u| u.connected && u.role == "admin" && u.session_id == session_id)
+        .count();
+    if admins >= max as usize {
+        return Err(fail(E_CONFLICT, "Admin limit reached"));
+    }
+    Ok(())
+}
+
+fn set_role(ctx: &ReducerContext, target_user_id: String, role: &str) -> Result<(), String> {
+    let user_table = ctx.db.user();
+    match user_table.user_id().find(&target_user_id) {
+        Some(user) => {
+            if role == "admin" && user.role != "admin" {
+                check_admin_limit(ctx, &user.session_id)?;
+            }
+            user_table.user_id().update(User {
+                role: role.to_string(),
+                ..user
+            });
+            Ok(())
+        }
+        None => Err(fail(E_NOT_FOUND, "User not found")),
+    }
+}
+
+// ======== Reducer Arguments ========
+
+#[derive(SpacetimeType, Serialize, Deserialize, Clone, Debug)]
+pub struct OptionInput {
+    pub text: String,
+    pub image_url: Option<String>,
+    pub metadata: Option<String>,
+}
+
+#[derive(SpacetimeType, Serialize, Deserialize, Clone, Debug)]
+pub struct DemoVoteCount {
+    pub option_id: u64,
+    pub count: u32,
+}
+
+fn set_response_hidden(ctx: &ReducerContext, response_id: u64, hidden: bool) -> Result<(), String> {
+    let response_table = ctx.db.text_response();
+    match response_table.response_id().find(response_id) {
+        Some(response) => {
+            response_table.response_id().update(with_hidden(response, hidden));
+            Ok(())
+        }
+        None => Err(fail(E_NOT_FOUND, "Response not found")),
+    }
+}
+
+// An admin decision; unhiding also resets the flag count
+fn with_hidden(response: TextResponse, hidden: bool) -> TextResponse {
+    let flag_count = if hidden { response.flag_count } else { 0 };
+    TextResponse {
+        hidden,
+        flag_count,
+        ..response
+    }
+}
+
+// One more flag, hiding the response once it reaches FLAG_HIDE_THRESHOLD
+fn with_flag(response: TextResponse) -> TextResponse {
+    let flag_count = response.flag_count + 1;
+    TextResponse {
+        flag_count,
+        hidden: response.hidden || flag_count >= FLAG_HIDE_THRESHOLD,
+        ..response
+    }
+}
+
+// ======== Reducers (Server-side functions) ========
+
+// A returning user's row after rejoining. The requested role only applies on
+// first join; later changes go through promote_user/demote_user.
+fn rejoined_user(existing: User, session_id: String, platform: Option<String>, now: i64) -> User {
+    User {
+        session_id,
+        connected_at: now,
+        connected: true,
+        last_seen: now,
+        platform: platform.or(existing.platform.clone()),
+        ..existing
+    }
+}
+
+// Whether a returning user is moving to another session, which clears their
+// votes when allowed
+fn check_session_switch(existing: &User, session_id: &str, settings: &Settings) -> Result<bool, String> {
+    // Rows from before normalization may still hold mixed-case IDs
+    if existing.session_id.to_lowercase() == session_id {
+        return Ok(false);
+    }
+    check_not_sealed(settings)?;
+    if !settings.clear_votes_on_session_switch {
+        return Err(fail(E_CONFLICT, "Already joined a different session"));
+    }
+    Ok(true)
+}
+
+// Whether a newcomer may join with `role`. Reconnecting users skip this.
+fn check_admission(settings: &Settings, role: &str, users: &[User]) -> Result<(), String> {
+    check_not_sealed(settings)?;
+    if settings.locked {
+        return Err(fail(E_CLOSED, "Session is locked"));
+    }
+    
+    // Enforce the participant cap; admins bypass it
+    if let Some(max) = settings.max_participants {
+        if role != "admin" {
+            let participants = users.iter()
+                .filter(|u| u.connected && u.role == "user")
+                .count();
+            if participants >= max as usize {
+                return Err(fail(E_CLOSED, "Session is full"));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn validate_platform(platform: &Option<String>) -> Result<(), String> {
+    if let Some(label) = platform {
+        if !PLATFORMS.contains(&label.as_str()) {
+            return Err(fail(E_INVALID_INPUT, format!("Unknown platform. Must be one of: {}", PLATFORMS.join(", "))));
+        }
+    }
+    Ok(())
+}
+
+#[reducer]
+pub fn join_session(ctx: &ReducerContext, session_id: String, role: String, platform: Option<String>) -> Result<(), String> {
+    throttle_calls(ctx)?;
+    
+    // Validate role
+    if role != "user" && role != "admin" {
+        return Err(fail(E_INVALID_INPUT, "Invalid role. Must be 'user' or 'admin'"));
+    }
+    validate_platform(&platform)?;
+    let session_id = normalize_session_id(&session_id)?;
+    
+    // Get user table handle
+    let user_table = ctx.db.user();
+    let user_id = ctx.sender.to_string();
+    let existing = user_table.user_id().find(&user_id);
+    let settings = get_settings(ctx);
+    
+    if existing.is_none() {
+        let users: Vec<User> = user_table.iter().collect();
+        check_admission(&settings, &role, &users)?;
+    }
+    
+    // Admins joining fresh, or reconnecting, count against the admin cap
+    let joins_as_admin = existing.as_ref().map_or(role == "admin", |u| u.role == "admin" && !u.connected);
+    if joins_as_admin {
+        check_admin_limit(ctx, &session_id)?;
+    }
+    
+    // A user belongs to one session at a time
+    if let Some(existing) = &existing {
+        if check_session_switch(existing, &session_id, &settings)? {
+            let removed = clear_user_votes(ctx, &user_id);
+            log::info!("User {} switched sessions; cleared {} votes", user_id, removed);
+        }
+    }
+    
+    // Make sure the session itself exists
+    let session_table = ctx.db.session_state();
+    if session_table.session_id().find(&session_id).is_none() {
+        check_not_sealed(&settings)?;
+        session_table.insert(SessionState {
+            session_id: session_id.clone(),
+            current_poll_id: 0,
+            state: "waiting".to_string(),
+        });
+    }
+    
+    // Check if user exists
+    if let Some(existing) = existing {
+        user_table.user_id().update(rejoined_user(existing, session_id, platform, ctx.timestamp.to_micros_since_unix_epoch()));
+    } else {
+        // Insert new user
+        user_table.insert(User {
+            user_id,
+            session_id,
+            role,
+            connected_at: ctx.timestamp.to_micros_since_unix_epoch(),
+            connected: true,
+            seat: None,
+            last_created_poll_at: None,
+            last_seen: ctx.timestamp.to_micros_since_unix_epoch(),
+            platform,
+            vote_weight: 1,
+        });
+    }
+    
+    // Initialize presentation state if it doesn't exist
+    let presentation_table = ctx.db.presentation_state();
+    if presentation_table.id().find(0).is_none() {
+        let initial_state = PresentationState {
+            id: 0,
+            current_poll_id: 0,
+            state: "waiting".to_string(),
+            reveal_at: None,
+        };
+        presentation_table.insert(initial_state);
+    }
+    
+    Ok(())
+}
+
+#[reducer]
+pub fn promote_user(ctx: &ReducerContext, target_user_id: String) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    require_admin(ctx, "Only admins can promote users")?;
+    set_role(ctx, target_user_id, "admin")
+}
+
+#[reducer]
+pub fn demote_user(ctx: &ReducerContext, target_user_id: String) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    require_admin(ctx, "Only admins can demote users")?;
+    
+    if target_user_id == ctx.sender.to_string() {
+        return Err(fail(E_FORBIDDEN, "Admins cannot demote themselves"));
+    }
+    set_role(ctx, target_user_id, "user")
+}
+
+fn check_bulk_role_change(from_role: &str, to_role: &str, confirm: bool) -> Result<(), String> {
+    for role in [from_role, to_role] {
+        if role != "user" && role != "admin" {
+            return Err(fail(E_INVALID_INPUT, "Invalid role. Must be 'user' or 'admin'"));
+        }
+    }
+    if from_role == to_role {
+        return Err(fail(E_INVALID_INPUT, "Source and target roles are the same"));
+    }
+    // Handing admin to a whole role is easy to do by mistake
+    if to_role == "admin" && !confirm {
+        return Err(fail(E_INVALID_INPUT, "Promoting a whole role to admin requires confirm = true"));
+    }
+    Ok(())
+}
+
+// The users in `from_role`, moved to `to_role`
+fn with_role_changed(users: Vec<User>, from_role: &str, to_role: &str) -> Vec<User> {
+    users.into_iter()
+        .filter(|u| u.role == from_role)
+        .map(|u| User {
+            role: to_role.to_string(),
+            ..u
+        })
+        .collect()
+}
+
+#[reducer]
+pub fn promote_all_in_role(ctx: &ReducerContext, from_role: String, to_role: String, confirm: bool) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    
+    require_superadmin(ctx)?;
+    
+    check_bulk_role_change(&from_role, &to_role, confirm)?;
+    
+    let user_table = ctx.db.user();
+    let users = user_table.iter().collect::<Vec<_>>();
+    let changed_users = with_role_changed(users, &from_role, &to_role);
+    let changed = changed_users.len();
+    for user in changed_users {
+        if to_role == "admin" {
+            check_admin_limit(ctx, &user.session_id)?;
+        }
+        user_table.user_id().update(user);
+    }
+    
+    log::info!("Changed {} users from {} to {}", changed, from_role, to_role);
+    Ok(())
+}
+
+#[reducer]
+pub fn assign_seat(ctx: &ReducerContext, target_user_id: String, seat: u32) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    require_admin(ctx, "Only admins can assign seats")?;
+    
+    if seat == 0 {
+        return Err(fail(E_INVALID_INPUT, "Seat must be a positive number"));
+    }
+    
+    let user_table = ctx.db.user();
+    match user_table.user_id().find(&target_user_id) {
+        Some(user) => {
+            user_table.user_id().update(User {
+                seat: Some(seat),
+                ..user
+            });
+            Ok(())
+        }
+        None => Err(fail(E_NOT_FOUND, "User not found")),
+    }
+}
+
+// Users per assigned seat, in order of first appearance
+fn seat_counts(users: &[User]) -> Vec<(u32, u64)> {
+    let mut counts: Vec<(u32, u64)> = Vec::new();
+    for seat in users.iter().filter_map(|u| u.seat) {
+        match counts.iter_mut().find(|(s, _)| *s == seat) {
+            Some(entry) => entry.1 += 1,
+            None => counts.push((seat, 1)),
+        }
+    }
+    counts
+}
+
+#[reducer]
+pub fn seat_distribution(ctx: &ReducerContext) -> Result<(), String> {
+    let users: Vec<User> = ctx.db.user().iter().collect();
+    let counts = seat_counts(&users);
+    
+    let seat_table = ctx.db.seat_count();
+    for row in seat_table.iter().collect::<Vec<_>>() {
+        seat_table.delete(row);
+    }
+    for (seat, user_count) in counts {
+        seat_table.insert(SeatCount { seat, user_count });
+    }
+    
+    Ok(())
+}
+
+// Users per reported platform, in first-seen order
+fn platform_counts(users: impl Iterator<Item = User>) -> Vec<(String, u64)> {
+    let mut counts: Vec<(String, u64)> = Vec::new();
+    for user in users {
+        let platform = user.platform.unwrap_or_else(|| "unknown".to_string());
+        match counts.iter_mut().find(|(p, _)| *p == platform) {
+            Some(entry) => entry.1 += 1,
+            None => counts.push((platform, 1)),
+        }
+    }
+    counts
+}
+
+#[reducer]
+pub fn platform_breakdown(ctx: &ReducerContext) -> Result<(), String> {
+    require_admin(ctx, "Only admins can view the platform breakdown")?;
+    
+    let counts = platform_counts(ctx.db.user().iter());
+    
+    let platform_table = ctx.db.platform_count();
+    for row in platform_table.iter().collect::<Vec<_>>() {
+        platform_table.delete(row);
+    }
+    for (platform, user_count) in counts {
+        platform_table.insert(PlatformCount { platform, user_count });
+    }
+    
+    Ok(())
+}
+
+#[reducer]
+pub fn set_auto_promote_admin(ctx: &ReducerContext, enabled: bool) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    require_admin(ctx, "Only admins can change settings")?;
+    
+    let settings = get_settings(ctx);
+    save_settings(ctx, Settings {
+        auto_promote_admin: enabled,
+        ..settings
+    });
+    
+    Ok(())
+}
+
+#[reducer]
+pub fn set_poll_cooldown(ctx: &ReducerContext, cooldown_millis: u64) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    require_admin(ctx, "Only admins can change settings")?;
+    
+    let settings = get_settings(ctx);
+    save_settings(ctx, Settings {
+        poll_cooldown_millis: cooldown_millis,
+        ..settings
+    });
+    
+    Ok(())
+}
+
+#[reducer]
+pub fn lock_session(ctx: &ReducerContext) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    require_admin(ctx, "Only admins can lock the session")?;
+    
+    let settings = get_settings(ctx);
+    save_settings(ctx, Settings {
+        locked: true,
+        ..settings
+    });
+    
+    Ok(())
+}
+
+#[reducer]
+pub fn unlock_session(ctx: &ReducerContext) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    require_admin(ctx, "Only admins can unlock the session")?;
+    
+    let settings = get_settings(ctx);
+    save_settings(ctx, Settings {
+        locked: false,
+        ..settings
+    });
+    
+    Ok(())
+}
+
+#[reducer]
+pub fn set_session_switch_policy(ctx: &ReducerContext, clear_votes: bool) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    require_admin(ctx, "Only admins can change settings")?;
+    
+    let settings = get_settings(ctx);
+    save_settings(ctx, Settings {
+        clear_votes_on_session_switch: clear_votes,
+        ..settings
+    });
+    
+    Ok(())
+}
+
+#[reducer]
+pub fn set_retention(ctx: &ReducerContext, retention_secs: u64) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    require_admin(ctx, "Only admins can change settings")?;
+    
+    let settings = get_settings(ctx);
+    save_settings(ctx, Settings {
+        retention_secs,
+        ..settings
+    });
+    ensure_cleanup_scheduled(ctx);
+    
+    Ok(())
+}
+
+// Archived copies of the polls that closed more than `retention_secs` ago
+fn expired_polls(polls: Vec<Poll>, now: i64, retention_secs: u64) -> Vec<Poll> {
+    let cutoff = now.saturating_sub((retention_secs as i64).saturating_mul(1_000_000));
+    polls.into_iter()
+        .filter(|p| !p.archived && !p.is_active && p.closed_at.is_some_and(|t| t < cutoff))
+        .map(|p| Poll {
+            archived: true,
+            ..p
+        })
+        .collect()
+}
+
+#[reducer]
+pub fn cleanup_expired(ctx: &ReducerContext, _schedule: CleanupSchedule) -> Result<(), String> {
+    if ctx.sender != ctx.identity() {
+        return Err(fail(E_FORBIDDEN, "cleanup_expired may only be invoked by the scheduler"));
+    }
+    let settings = get_settings(ctx);
+    if settings.sealed {
+        return Ok(());
+    }
+    
+    // Only archives; results, checksums and other snapshots stay in place
+    let poll_table = ctx.db.poll();
+    let polls = poll_table.iter().collect::<Vec<_>>();
+    let expired = expired_polls(polls, ctx.timestamp.to_micros_since_unix_epoch(), settings.retention_secs);
+    let archived = expired.len();
+    for poll in expired {
+        poll_table.poll_id().update(poll);
+    }
+    
+    if archived > 0 {
+        log::info!("cleanup_expired: archived {} polls", archived);
+    }
+    Ok(())
+}
+
+#[reducer]
+pub fn set_rate_limit(ctx: &ReducerContext, max_calls_per_window: u32) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    require_admin(ctx, "Only admins can change settings")?;
+    
+    let settings = get_settings(ctx);
+    save_settings(ctx, Settings {
+        max_calls_per_window,
+        ..settings
+    });
+    
+    Ok(())
+}
+
+#[reducer]
+pub fn set_log_rejected(ctx: &ReducerContext, enabled: bool) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    require_admin(ctx, "Only admins can change settings")?;
+    
+    let settings = get_settings(ctx);
+    save_settings(ctx, Settings {
+        log_rejected: enabled,
+        ..settings
+    });
+    
+    Ok(())
+}
+
+#[reducer]
+pub fn set_max_admins(ctx: &ReducerContext, max_admins: u32) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    require_admin(ctx, "Only admins can change settings")?;
+    
+    let settings = get_settings(ctx);
+    save_settings(ctx, Settings {
+        max_admins,
+        ..settings
+    });
+    
+    Ok(())
+}
+
+#[reducer]
+pub fn set_grace_period(ctx: &ReducerContext, grace_secs: u64) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    require_admin(ctx, "Only admins can change settings")?;
+    
+    let settings = get_settings(ctx);
+    save_settings(ctx, Settings {
+        grace_secs,
+        ..settings
+    });
+    
+    Ok(())
+}
+
+// Debug mode lets anyone pick their own role, so only the superadmin may enable it
+#[reducer]
+pub fn set_debug_mode(ctx: &ReducerContext, enabled: bool) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    
+    require_superadmin(ctx)?;
+    
+    let settings = get_settings(ctx);
+    save_settings(ctx, Settings {
+        debug_mode: enabled,
+        ..settings
+    });
+    
+    Ok(())
+}
+
+fn check_role_switch(settings: &Settings, role: &str) -> Result<(), String> {
+    if !settings.debug_mode {
+        return Err(fail(E_FORBIDDEN, "set_my_role is only available in debug mode"));
+    }
+    if role != "user" && role != "admin" {
+        return Err(fail(E_INVALID_INPUT, "Invalid role. Must be 'user' or 'admin'"));
+    }
+    Ok(())
+}
+
+#[reducer]
+pub fn set_my_role(ctx: &ReducerContext, role: String) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    
+    check_role_switch(&get_settings(ctx), &role)?;
+    set_role(ctx, ctx.sender.to_string(), &role)
+}
+
+fn demo_vote_total(distribution: &[DemoVoteCount]) -> Result<u64, String> {
+    let total: u64 = distribution.iter().map(|e| e.count as u64).sum();
+    if total > MAX_DEMO_VOTES {
+        return Err(fail(E_INVALID_INPUT, format!("At most {} demo votes can be seeded at once", MAX_DEMO_VOTES)));
+    }
+    Ok(total)
+}
+
+// Vote IDs are unique, so they make unique pseudo-voters too
+fn demo_votes(poll_id: u64, distribution: &[DemoVoteCount], first_vote_id: u64, now: i64) -> Vec<Vote> {
+    let mut vote_id = first_vote_id;
+    let mut votes = Vec::new();
+    for entry in distribution {
+        for _ in 0..entry.count {
+            votes.push(Vote {
+                vote_id,
+                poll_id,
+                user_id: format!("demo-{}", vote_id),
+                option_id: entry.option_id,
+                voted_at: now,
+                source: None,
+                change_count: 0,
+                voided: false,
+                provisional: false,
+                weight: 1,
+                cast_by: None,
+                is_demo: true,
+            });
+            vote_id += 1;
+        }
+    }
+    votes
+}
+
+// Fills a poll with synthetic votes for rehearsals; undo with clear_demo_votes
+#[reducer]
+pub fn seed_demo_votes(ctx: &ReducerContext, poll_id: u64, distribution: Vec<DemoVoteCount>) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    require_admin(ctx, "Only admins can seed demo votes")?;
+    
+    if !get_settings(ctx).debug_mode {
+        return Err(fail(E_FORBIDDEN, "seed_demo_votes is only available in debug mode"));
+    }
+    if ctx.db.poll().poll_id().find(poll_id).is_none() {
+        return Err(fail(E_NOT_FOUND, "Poll not found"));
+    }
+    for entry in distribution.iter() {
+        resolve_option(ctx, poll_id, entry.option_id)?;
+    }
+    let total = demo_vote_total(&distribution)?;
+    if total == 0 {
+        return Ok(());
+    }
+    
+    let first_vote_id = reserve_ids(ctx, "vote", total);
+    let vote_table = ctx.db.vote();
+    for vote in demo_votes(poll_id, &distribution, first_vote_id, ctx.timestamp.to_micros_since_unix_epoch()) {
+        extend_checksum(ctx, poll_id, vote.vote_id, vote.option_id);
+        vote_table.insert(vote);
+    }
+    for entry in distribution {
+        adjust_result(ctx, poll_id, entry.option_id, entry.count as i64);
+    }
+    
+    log::info!("Seeded {} demo votes into poll {}", total, poll_id);
+    Ok(())
+}
+
+#[reducer]
+pub fn clear_demo_votes(ctx: &ReducerContext, poll_id: u64) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    require_admin(ctx, "Only admins can clear demo votes")?;
+    
+    let vote_table = ctx.db.vote();
+    let demo_votes = vote_table.iter()
+        .filter(|v| v.poll_id == poll_id && v.is_demo)
+        .collect::<Vec<_>>();
+    for vote in demo_votes.iter() {
+        vote_table.vote_id().delete(vote.vote_id);
+        extend_checksum(ctx, poll_id, vote.vote_id, VOTE_REMOVED);
+        if !vote.voided {
+            adjust_result(ctx, poll_id, vote.option_id, -(vote.weight as i64));
+        }
+    }
+    
+    log::info!("Cleared {} demo votes from poll {}", demo_votes.len(), poll_id);
+    Ok(())
+}
+
+#[reducer]
+pub fn set_max_participants(ctx: &ReducerContext, max_participants: Option<u32>) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    require_admin(ctx, "Only admins can change settings")?;
+    
+    let settings = get_settings(ctx);
+    save_settings(ctx, Settings {
+        max_participants,
+        ..settings
+    });
+    
+    Ok(())
+}
+
+#[reducer]
+pub fn create_poll(ctx: &ReducerContext, question: String, options: Vec<String>) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    
+    // Check if user is admin
+    let user = require_admin(ctx, "Only admins can create polls")?;
+    
+    let options = options.into_iter()
+        .map(|text| OptionInput {
+            text,
+            image_url: None,
+            metadata: None,
+        })
+        .collect::<Vec<_>>();
+    validate_question(&question)?;
+    validate_options(&options)?;
+    enforce_poll_cooldown(ctx, user)?;
+    
+    insert_poll(ctx, question, options);
+    
+    // Return success - the poll_id has been stored in the database
+    Ok(())
+}
+
+#[reducer]
+pub fn create_poll_rich(ctx: &ReducerContext, question: String, options: Vec<OptionInput>) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    
+    let user = require_admin(ctx, "Only admins can create polls")?;
+    
+    validate_question(&question)?;
+    validate_options(&options)?;
+    enforce_poll_cooldown(ctx, user)?;
+    
+    insert_poll(ctx, question, options);
+    Ok(())
+}
+
+#[reducer]
+pub fn edit_poll_question(ctx: &ReducerContext, poll_id: u64, question: String) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    require_admin(ctx, "Only admins can edit polls")?;
+    validate_question(&question)?;
+    
+    let poll_table = ctx.db.poll();
+    match poll_table.poll_id().find(poll_id) {
+        Some(poll) => {
+            poll_table.poll_id().update(Poll { question, ..poll });
+            Ok(())
+        }
+        None => Err(fail(E_NOT_FOUND, "Poll not found")),
+    }
+}
+
+#[reducer]
+pub fn edit_option_text(ctx: &ReducerContext, option_id: u64, text: String) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    require_admin(ctx, "Only admins can edit options")?;
+    validate_option_text(&text)?;
+    
+    let option_table = ctx.db.poll_option();
+    match option_table.option_id().find(option_id) {
+        Some(option) => {
+            option_table.option_id().update(PollOption { text, ..option });
+            Ok(())
+        }
+        None => Err(fail(E_NOT_FOUND, "Option not found")),
+    }
+}
+
+#[reducer]
+pub fn add_option(ctx: &ReducerContext, poll_id: u64, text: String) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    require_admin(ctx, "Only admins can add options")?;
+    validate_option_text(&text)?;
+    
+    match ctx.db.poll().poll_id().find(poll_id) {
+        Some(poll) if poll.archived => Err(fail(E_CONFLICT, "Poll is archived")),
+        Some(_) if ctx.db.poll_option().iter().filter(|o| o.poll_id == poll_id).count() >= MAX_OPTIONS_PER_POLL => {
+            Err(fail(E_INVALID_INPUT, format!("A poll can have at most {} options", MAX_OPTIONS_PER_POLL)))
+        }
+        Some(_) => {
+            insert_option(ctx, poll_id, OptionInput {
+                text,
+                image_url: None,
+                metadata: None,
+            });
+            Ok(())
+        }
+        None => Err(fail(E_NOT_FOUND, "Poll not found")),
+    }
+}
+
+// Archived polls stay closed and drafts must be published first
+fn check_activatable(poll: &Poll) -> Result<(), String> {
+    if poll.archived {
+        return Err(fail(E_CONFLICT, "Poll is archived"));
+    }
+    if poll.is_draft {
+        return Err(fail(E_CONFLICT, "Poll is a draft; publish it first"));
+    }
+    Ok(())
+}
+
+// Votes for the removed option, split into the IDs to delete and the votes
+// moved to the kept option. A user who already backs the kept option keeps
+// just that vote.
+fn merged_votes(votes: &[Vote], keep_option_id: u64, remove_option_id: u64) -> (Vec<u64>, Vec<Vote>) {
+    let mut duplicates = Vec::new();
+    let mut moved = Vec::new();
+    for vote in votes.iter().filter(|v| v.option_id == remove_option_id) {
+        let has_kept = votes.iter()
+            .any(|v| v.user_id == vote.user_id && v.option_id == keep_option_id);
+        if has_kept {
+            duplicates.push(vote.vote_id);
+        } else {
+            moved.push(Vote {
+                option_id: keep_option_id,
+                ..vote.clone()
+            });
+        }
+    }
+    (duplicates, moved)
+}
+
+// Ranked ballots list each option once, so drop the removed one if both appear
+fn merged_ranking(ranking: &[u64], keep_option_id: u64, remove_option_id: u64) -> Vec<u64> {
+    let mut merged: Vec<u64> = Vec::new();
+    for id in ranking.iter().map(|&id| if id == remove_option_id { keep_option_id } else { id }) {
+        if !merged.contains(&id) {
+            merged.push(id);
+        }
+    }
+    merged
+}
+
+#[reducer]
+pub fn merge_options(ctx: &ReducerContext, keep_option_id: u64, remove_option_id: u64) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    require_admin(ctx, "Only admins can merge options")?;
+    
+    if keep_option_id == remove_option_id {
+        return Err(fail(E_INVALID_INPUT, "Cannot merge an option with itself"));
+    }
+    let option_table = ctx.db.poll_option();
+    let keep = match option_table.option_id().find(keep_option_id) {
+        Some(option) => option,
+        None => return Err(fail(E_NOT_FOUND, "Option not found")),
+    };
+    let poll_id = keep.poll_id;
+    resolve_option(ctx, poll_id, remove_option_id)?;
+    
+    let poll_table = ctx.db.poll();
+    let poll = match poll_table.poll_id().find(poll_id) {
+        Some(poll) => poll,
+        None => return Err(fail(E_NOT_FOUND, "Poll not found")),
+    };
+    if poll.is_active {
+        return Err(fail(E_CONFLICT, "Close the poll before merging options"));
+    }
+    
+    let vote_table = ctx.db.vote();
+    let votes = vote_table.iter()
+        .filter(|v| v.poll_id == poll_id)
+        .collect::<Vec<_>>();
+    let (duplicates, moved) = merged_votes(&votes, keep_option_id, remove_option_id);
+    for vote_id in duplicates {
+        vote_table.vote_id().delete(vote_id);
+        extend_checksum(ctx, poll_id, vote_id, VOTE_REMOVED);
+    }
+    for vote in moved {
+        extend_checksum(ctx, poll_id, vote.vote_id, vote.option_id);
+        vote_table.vote_id().update(vote);
+    }
+    
+    let ballot_table = ctx.db.ranked_ballot();
+    for ballot in ballot_table.poll_id().filter(poll_id).collect::<Vec<_>>() {
+        if !ballot.ranking.contains(&remove_option_id) {
+            continue;
+        }
+        let ranking = merged_ranking(&ballot.ranking, keep_option_id, remove_option_id);
+        ballot_table.ballot_id().update(RankedBallot { ranking, ..ballot });
+    }
+    
+    // Choice ballots stay sorted and deduplicated
+    let choice_table = ctx.db.choice_ballot();
+    for ballot in choice_table.poll_id().filter(poll_id).collect::<Vec<_>>() {
+        if !ballot.option_ids.contains(&remove_option_id) {
+            continue;
+        }
+        let mut option_ids = ballot.option_ids.iter()
+            .map(|&id| if id == remove_option_id { keep_option_id } else { id })
+            .collect::<Vec<_>>();
+        option_ids.sort();
+        option_ids.dedup();
+        choice_table.ballot_id().update(ChoiceBallot { option_ids, ..ballot });
+    }
+    
+    if poll.correct_option_id == Some(remove_option_id) {
+        poll_table.poll_id().update(Poll {
+            correct_option_id: Some(keep_option_id),
+            ..poll
+        });
+    }
+    
+    option_table.option_id().delete(remove_option_id);
+    recount_results(ctx, poll_id);
+    
+    Ok(())
+}
+
+#[reducer]
+pub fn activate_poll(ctx: &ReducerContext, poll_id: u64) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    
+    // Check if user is admin
+    let user_table = ctx.db.user();
+    if let Some(user) = user_table.user_id().find(ctx.sender.to_string()) {
+        if user.role != "admin" {
+            return Err(fail(E_FORBIDDEN, "Only admins can activate polls"));
+        }
+    } else {
+        return Err(fail(E_NOT_FOUND, "User not found"));
+    }
+    
+    // Check if poll exists
+    if let Some(poll) = ctx.db.poll().poll_id().find(poll_id) {
+        check_activatable(&poll)?;
+        check_prerequisite(ctx, &poll)?;
+        present_poll(ctx, poll);
+        Ok(())
+    } else {
+        Err(fail(E_NOT_FOUND, "Poll not found"))
+    }
+}
+
+#[reducer]
+pub fn open_poll(ctx: &ReducerContext, poll_id: u64) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    require_admin(ctx, "Only admins can open polls")?;
+    
+    if get_settings(ctx).guided_mode {
+        return Err(fail(E_CONFLICT, "Polls are opened with activate_poll in guided mode"));
+    }
+    
+    // Open the poll for voting without touching the presentation state
+    let poll_table = ctx.db.poll();
+    match poll_table.poll_id().find(poll_id) {
+        Some(poll) => {
+            check_activatable(&poll)?;
+            check_prerequisite(ctx, &poll)?;
+            ctx.db.poll_open_schedule().poll_id().delete(poll_id);
+            if !poll.is_active {
+                poll_table.poll_id().update(Poll {
+                    is_active: true,
+                    activated_at: Some(ctx.timestamp.to_micros_since_unix_epoch()),
+                    closed_at: None,
+                    close_reason: None,
+                    scheduled_open_at: None,
+                    ..poll
+                });
+            } else if poll.scheduled_open_at.is_some() {
+                poll_table.poll_id().update(Poll {
+                    scheduled_open_at: None,
+                    ..poll
+                });
+            }
+            Ok(())
+        }
+        None => Err(fail(E_NOT_FOUND, "Poll not found")),
+    }
+}
+
+#[reducer]
+pub fn close_poll(ctx: &ReducerContext, poll_id: u64, close_reason: Option<String>) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    require_admin(ctx, "Only admins can close polls")?;
+    let close_reason = validate_close_reason(close_reason)?;
+    
+    match ctx.db.poll().poll_id().find(poll_id) {
+        Some(poll) => {
+            if poll.is_active {
+                deactivate_poll(ctx, poll, close_reason);
+            }
+            Ok(())
+        }
+        None => Err(fail(E_NOT_FOUND, "Poll not found")),
+    }
+}
+
+#[reducer]
+pub fn archive_poll(ctx: &ReducerContext, poll_id: u64) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    require_admin(ctx, "Only admins can archive polls")?;
+    
+    let poll_table = ctx.db.poll();
+    match poll_table.poll_id().find(poll_id) {
+        Some(poll) => {
+            let poll = if poll.is_active {
+                deactivate_poll(ctx, poll, None)
+            } else {
+                poll
+            };
+            poll_table.poll_id().update(Poll {
+                archived: true,
+                ..poll
+            });
+            Ok(())
+        }
+        None => Err(fail(E_NOT_FOUND, "Poll not found")),
+    }
+}
+
+// The next shown poll in creation order, or the first one if nothing has been shown yet
+fn next_in_order<'a>(polls: &'a [Poll], current: Option<&Poll>) -> Option<&'a Poll> {
+    let after = current.map(|p| (p.created_at, p.poll_id));
+    polls.iter()
+        .filter(|p| !p.archived && !p.is_draft)
+        .filter(|p| after.is_none_or(|a| (p.created_at, p.poll_id) > a))
+        .min_by_key(|p| (p.created_at, p.poll_id))
+}
+
+// The shown poll created just before `current`, if any
+fn previous_in_order<'a>(polls: &'a [Poll], current: &Poll) -> Option<&'a Poll> {
+    let before = (current.created_at, current.poll_id);
+    polls.iter()
+        .filter(|p| !p.archived && !p.is_draft && (p.created_at, p.poll_id) < before)
+        .max_by_key(|p| (p.created_at, p.poll_id))
+}
+
+#[reducer]
+pub fn next_poll(ctx: &ReducerContext) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    require_admin(ctx, "Only admins can advance polls")?;
+    
+    let presentation_table = ctx.db.presentation_state();
+    let state = match presentation_table.id().find(0) {
+        Some(state) => state,
+        None => return Err(fail(E_NOT_FOUND, "Presentation state not found")),
+    };
+    
+    // Close the current poll
+    let poll_table = ctx.db.poll();
+    let current = poll_table.poll_id().find(state.current_poll_id);
+    if let Some(poll) = current.clone() {
+        if poll.is_active {
+            deactivate_poll(ctx, poll, None);
+        }
+    }
+    
+    let polls: Vec<Poll> = poll_table.iter().collect();
+    let next = next_in_order(&polls, current.as_ref()).cloned();
+    
+    match next {
+        Some(poll) => {
+            check_prerequisite(ctx, &poll)?;
+            present_poll(ctx, poll);
+        }
+        None => {
+            presentation_table.id().update(PresentationState {
+                state: "ended".to_string(),
+                reveal_at: None,
+                ..state
+            });
+        }
+    }
+    
+    Ok(())
+}
+
+// Steps back to the previous poll. With `reopen` the poll is activated again
+// for re-voting; otherwise it is only redisplayed with its results.
+#[reducer]
+pub fn previous_poll(ctx: &ReducerContext, reopen: bool) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    require_admin(ctx, "Only admins can go back to a previous poll")?;
+    
+    let presentation_table = ctx.db.presentation_state();
+    let state = match presentation_table.id().find(0) {
+        Some(state) => state,
+        None => return Err(fail(E_NOT_FOUND, "Presentation state not found")),
+    };
+    
+    let poll_table = ctx.db.poll();
+    let current = match poll_table.poll_id().find(state.current_poll_id) {
+        Some(poll) => poll,
+        None => return Err(fail(E_CONFLICT, "No poll has been shown yet")),
+    };
+    
+    let polls: Vec<Poll> = poll_table.iter().collect();
+    let previous = match previous_in_order(&polls, &current).cloned() {
+        Some(poll) => poll,
+        None => return Err(fail(E_CONFLICT, "Already at the first poll")),
+    };
+    if reopen {
+        check_prerequisite(ctx, &previous)?;
+    }
+    
+    // Close the current poll
+    if current.is_active {
+        deactivate_poll(ctx, current, None);
+    }
+    
+    if reopen {
+        present_poll(ctx, previous);
+    } else {
+        presentation_table.id().update(PresentationState {
+            id: 0,
+            current_poll_id: previous.poll_id,
+            state: "results".to_string(),
+            reveal_at: None,
+        });
+    }
+    
+    Ok(())
+}
+
+#[reducer]
+pub fn schedule_poll_open(ctx: &ReducerContext, poll_id: u64, open_at: i64) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    require_admin(ctx, "Only admins can schedule polls")?;
+    
+    if open_at <= ctx.timestamp.to_micros_since_unix_epoch() {
+        return Err(fail(E_INVALID_INPUT, "Scheduled open time must be in the future"));
+    }
+    
+    let poll_table = ctx.db.poll();
+    let poll = match poll_table.poll_id().find(poll_id) {
+        Some(poll) => poll,
+        None => return Err(fail(E_NOT_FOUND, "Poll not found")),
+    };
+    if poll.archived {
+        return Err(fail(E_CONFLICT, "Poll is archived"));
+    }
+    
+    // Rescheduling replaces any earlier schedule for this poll
+    let schedule_table = ctx.db.poll_open_schedule();
+    schedule_table.poll_id().delete(poll_id);
+    schedule_table.insert(PollOpenSchedule {
+        scheduled_id: 0,
+        scheduled_at: Timestamp::from_micros_since_unix_epoch(open_at).into(),
+        poll_id,
+    });
+    
+    poll_table.poll_id().update(Poll {
+        scheduled_open_at: Some(open_at),
+        ..poll
+    });
+    
+    Ok(())
+}
+
+#[reducer]
+pub fn open_scheduled_poll(ctx: &ReducerContext, schedule: PollOpenSchedule) -> Result<(), String> {
+    if ctx.sender != ctx.identity() {
+        return Err(fail(E_FORBIDDEN, "open_scheduled_poll may only be invoked by the scheduler"));
+    }
+    if get_settings(ctx).sealed {
+        return Ok(());
+    }
+    
+    let poll = match ctx.db.poll().poll_id().find(schedule.poll_id) {
+        Some(poll) => poll,
+        None => return Ok(()), // Poll was deleted in the meantime
+    };
+    if poll.archived {
+        return Ok(());
+    }
+    if poll.is_draft {
+        log::warn!("Not opening scheduled poll {}: still a draft", poll.poll_id);
+        return Ok(());
+    }
+    if let Err(e) = check_prerequisite(ctx, &poll) {
+        log::warn!("Not opening scheduled poll {}: {}", poll.poll_id, e);
+        return Ok(());
+    }
+    
+    present_poll(ctx, Poll {
+        scheduled_open_at: None,
+        ..poll
+    });
+    Ok(())
+}
+
+#[reducer]
+pub fn enqueue_poll(ctx: &ReducerContext, poll_id: u64) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    require_admin(ctx, "Only admins can queue polls")?;
+    
+    match ctx.db.poll().poll_id().find(poll_id) {
+        Some(poll) if poll.archived => return Err(fail(E_CONFLICT, "Poll is archived")),
+        Some(_) => {}
+        None => return Err(fail(E_NOT_FOUND, "Poll not found")),
+    }
+    
+    let queue_table = ctx.db.poll_queue();
+    if queue_table.poll_id().find(poll_id).is_some() {
+        return Err(fail(E_CONFLICT, "Poll is already queued"));
+    }
+    queue_table.insert(PollQueue {
+        queue_id: 0,
+        poll_id,
+    });
+    
+    Ok(())
+}
+
+// Pops entries in order, skipping polls deleted or archived since they were
+// queued; activate_poll would refuse the latter too. Returns the popped
+// queue_ids and the poll to present, if any.
+fn pop_queue(mut entries: Vec<PollQueue>, find_poll: impl Fn(u64) -> Option<Poll>) -> (Vec<u64>, Option<Poll>) {
+    entries.sort_by_key(|q| q.queue_id);
+    let mut popped = Vec::new();
+    for entry in entries {
+        popped.push(entry.queue_id);
+        match find_poll(entry.poll_id) {
+            Some(poll) if poll.archived => {
+                log::info!("Skipping queued poll {}: archived", poll.poll_id);
+            }
+            Some(poll) => return (popped, Some(poll)),
+            None => {}
+        }
+    }
+    (popped, None)
+}
+
+#[reducer]
+pub fn dequeue_and_activate(ctx: &ReducerContext) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    require_admin(ctx, "Only admins can advance the poll queue")?;
+    
+    let queue_table = ctx.db.poll_queue();
+    let entries = queue_table.iter().collect::<Vec<_>>();
+    let (popped, next) = pop_queue(entries, |poll_id| ctx.db.poll().poll_id().find(poll_id));
+    for queue_id in popped {
+        queue_table.queue_id().delete(queue_id);
+    }
+    let poll = match next {
+        Some(poll) => poll,
+        None => return Err(fail(E_CONFLICT, "Poll queue is empty")),
+    };
+    if poll.is_draft {
+        return Err(fail(E_CONFLICT, "Poll is a draft; publish it first"));
+    }
+    check_prerequisite(ctx, &poll)?;
+    
+    // Close the prior poll, even when other polls may stay open
+    if let Some(state) = ctx.db.presentation_state().id().find(0) {
+        if let Some(prior) = ctx.db.poll().poll_id().find(state.current_poll_id) {
+            if prior.is_active && prior.poll_id != poll.poll_id {
+                deactivate_poll(ctx, prior, None);
+            }
+        }
+    }
+    
+    present_poll(ctx, poll);
+    Ok(())
+}
+
+#[reducer]
+pub fn set_guided_mode(ctx: &ReducerContext, enabled: bool) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    require_admin(ctx, "Only admins can change settings")?;
+    
+    let settings = get_settings(ctx);
+    save_settings(ctx, Settings {
+        guided_mode: enabled,
+        ..settings
+    });
+    
+    Ok(())
+}
+
+#[reducer]
+pub fn set_anonymous_reports(ctx: &ReducerContext, poll_id: u64, anonymous_reports: bool) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    require_admin(ctx, "Only admins can change report anonymity")?;
+    
+    let poll_table = ctx.db.poll();
+    let poll = match poll_table.poll_id().find(poll_id) {
+        Some(poll) => poll,
+        None => return Err(fail(E_NOT_FOUND, "Poll not found")),
+    };
+    
+    // Voters were told one thing when they voted; don't change it afterwards
+    if ctx.db.vote().iter().any(|v| v.poll_id == poll_id) {
+        return Err(fail(E_CONFLICT, "Cannot change report anonymity after votes have been cast"));
+    }
+    
+    poll_table.poll_id().update(Poll { anonymous_reports, ..poll });
+    Ok(())
+}
+
+#[reducer]
+pub fn set_allow_change(ctx: &ReducerContext, poll_id: u64, allow_change: bool) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    require_admin(ctx, "Only admins can change the vote-change policy")?;
+    
+    let poll_table = ctx.db.poll();
+    match poll_table.poll_id().find(poll_id) {
+        Some(poll) => {
+            poll_table.poll_id().update(Poll { allow_change, ..poll });
+            Ok(())
+        }
+        None => Err(fail(E_NOT_FOUND, "Poll not found")),
+    }
+}
+
+// Whether the poll takes votes at `now`: past its scheduled open, inside its
+// voting window, and active or within grace_secs of closing
+fn voting_open_at(poll: &Poll, now: i64, grace_secs: u64) -> Result<(), String> {
+    if poll.scheduled_open_at.is_some_and(|t| t > now) {
+        return Err(fail(E_CLOSED, "Voting hasn't started yet"));
+    }
+    if poll.window_start.is_some_and(|t| now < t) || poll.window_end.is_some_and(|t| now >= t) {
+        return Err(fail(E_CLOSED, "Outside the voting window"));
+    }
+    if !poll.is_active {
+        // Let in-flight votes land for a short while after the poll closes
+        let grace_micros = (grace_secs as i64).saturating_mul(1_000_000);
+        let in_grace = !poll.archived && poll.closed_at
+            .is_some_and(|t| now < t.saturating_add(grace_micros));
+        if !in_grace {
+            return Err(fail(E_CLOSED, "Poll is not active"));
+        }
+    }
+    Ok(())
+}
+
+// Keeps source labels to a known set so breakdowns stay small
+fn validate_source(source: &Option<String>) -> Result<(), String> {
+    if let Some(label) = source {
+        if !VOTE_SOURCES.contains(&label.as_str()) {
+            return Err(fail(E_INVALID_INPUT, format!("Unknown vote source. Must be one of: {}", VOTE_SOURCES.join(", "))));
+        }
+    }
+    Ok(())
+}
+
+// The result rows a vote for `option_id` at `weight` touches, so subscribers
+// only see the one or two options that actually moved
+fn result_deltas(existing: Option<&Vote>, option_id: u64, provisional: bool, weight: u32) -> Vec<(u64, i64)> {
+    let mut deltas = Vec::new();
+    let counted = !provisional;
+    match existing {
+        // Move the count over only if the choice, its weight or its counted
+        // status changed
+        Some(existing) => {
+            let was_counted = !existing.provisional;
+            if existing.option_id != option_id || existing.weight != weight || was_counted != counted {
+                if was_counted {
+                    deltas.push((existing.option_id, -(existing.weight as i64)));
+                }
+                if counted {
+                    deltas.push((option_id, weight as i64));
+                }
+            }
+        }
+        None if counted => deltas.push((option_id, weight as i64)),
+        None => {}
+    }
+    deltas
+}
+
+// Whether `existing` may be replaced by a vote for `option_id`; returns
+// whether the choice itself changes
+fn check_vote_change(poll: &Poll, existing: &Vote, option_id: u64, provisional: bool) -> Result<bool, String> {
+    if existing.voided {
+        return Err(fail(E_CONFLICT, "Your vote has been voided by a moderator"));
+    }
+    if provisional && !existing.provisional {
+        return Err(fail(E_CONFLICT, "Vote is already confirmed"));
+    }
+    // A provisional vote hasn't counted yet, so it may always be revised
+    if !poll.allow_change && !existing.provisional {
+        return Err(fail(E_CONFLICT, "Vote already cast and changes are disabled"));
+    }
+    
+    let changed = existing.option_id != option_id;
+    if changed && poll.max_changes.is_some_and(|max| existing.change_count >= max) {
+        return Err(fail(E_CONFLICT, "Maximum vote changes reached"));
+    }
+    Ok(changed)
+}
+
+#[reducer]
+pub fn set_public_ballots(ctx: &ReducerContext, poll_id: u64, public_ballots: bool) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    require_admin(ctx, "Only admins can change ballot visibility")?;
+    
+    let poll_table = ctx.db.poll();
+    match poll_table.poll_id().find(poll_id) {
+        Some(poll) => {
+            poll_table.poll_id().update(Poll { public_ballots, ..poll });
+            // Take down anything already listed for everyone
+            if !public_ballots {
+                ctx.db.poll_ballot().poll_id().delete(poll_id);
+            }
+            Ok(())
+        }
+        None => Err(fail(E_NOT_FOUND, "Poll not found")),
+    }
+}
+
+#[reducer]
+pub fn set_allow_write_ins(ctx: &ReducerContext, poll_id: u64, allow_write_ins: bool) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    require_admin(ctx, "Only admins can change write-in settings")?;
+    
+    let poll_table = ctx.db.poll();
+    match poll_table.poll_id().find(poll_id) {
+        Some(poll) => {
+            poll_table.poll_id().update(Poll { allow_write_ins, ..poll });
+            Ok(())
+        }
+        None => Err(fail(E_NOT_FOUND, "Poll not found")),
+    }
+}
+
+// The option a write-in resolves to: an existing one with the same text, or
+// None if the user may still add a new one
+fn existing_write_in(options: &[PollOption], text: &str, user_id: &str) -> Result<Option<u64>, String> {
+    let key = normalize_text(text);
+    if let Some(option) = options.iter().find(|o| normalize_text(&o.text) == key) {
+        return Ok(Some(option.option_id));
+    }
+    let written = options.iter()
+        .filter(|o| o.added_by.as_deref() == Some(user_id))
+        .count();
+    if written >= MAX_WRITE_INS_PER_USER {
+        return Err(fail(E_INVALID_INPUT, format!("You can add at most {} write-ins per poll", MAX_WRITE_INS_PER_USER)));
+    }
+    Ok(None)
+}
+
+#[reducer]
+pub fn set_correct_answer(ctx: &ReducerContext, poll_id: u64, option_id: u64) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    require_admin(ctx, "Only admins can set the correct answer")?;
+    
+    let poll_table = ctx.db.poll();
+    let poll = match poll_table.poll_id().find(poll_id) {
+        Some(poll) => poll,
+        None => return Err(fail(E_NOT_FOUND, "Poll not found")),
+    };
+    resolve_option(ctx, poll_id, option_id)?;
+    
+    poll_table.poll_id().update(Poll {
+        correct_option_id: Some(option_id),
+        ..poll
+    });
+    Ok(())
+}
+
+#[reducer]
+pub fn set_max_votes(ctx: &ReducerContext, poll_id: u64, max_votes: Option<u32>) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    require_admin(ctx, "Only admins can change the vote cap")?;
+    
+    let poll_table = ctx.db.poll();
+    match poll_table.poll_id().find(poll_id) {
+        Some(poll) => {
+            poll_table.poll_id().update(Poll { max_votes, ..poll });
+            Ok(())
+        }
+        None => Err(fail(E_NOT_FOUND, "Poll not found")),
+    }
+}
+
+#[reducer]
+pub fn set_max_changes(ctx: &ReducerContext, poll_id: u64, max_changes: Option<u32>) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    require_admin(ctx, "Only admins can change the vote change limit")?;
+    
+    let poll_table = ctx.db.poll();
+    match poll_table.poll_id().find(poll_id) {
+        Some(poll) => {
+            poll_table.poll_id().update(Poll { max_changes, ..poll });
+            Ok(())
+        }
+        None => Err(fail(E_NOT_FOUND, "Poll not found")),
+    }
+}
+
+#[reducer]
+pub fn set_quorum(ctx: &ReducerContext, poll_id: u64, quorum: Option<u32>) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    require_admin(ctx, "Only admins can set a quorum")?;
+    
+    let poll_table = ctx.db.poll();
+    match poll_table.poll_id().find(poll_id) {
+        Some(poll) => {
+            poll_table.poll_id().update(Poll { quorum, ..poll });
+            Ok(())
+        }
+        None => Err(fail(E_NOT_FOUND, "Poll not found")),
+    }
+}
+
+#[reducer]
+pub fn set_creator_can_vote(ctx: &ReducerContext, poll_id: u64, creator_can_vote: bool) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    require_admin(ctx, "Only admins can change who may vote")?;
+    
+    let poll_table = ctx.db.poll();
+    match poll_table.poll_id().find(poll_id) {
+        Some(poll) => {
+            poll_table.poll_id().update(Poll { creator_can_vote, ..poll });
+            Ok(())
+        }
+        None => Err(fail(E_NOT_FOUND, "Poll not found")),
+    }
+}
+
+#[reducer]
+pub fn set_poll_group(ctx: &ReducerContext, poll_id: u64, group_id: Option<u64>) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    require_admin(ctx, "Only admins can group polls")?;
+    
+    let poll_table = ctx.db.poll();
+    match poll_table.poll_id().find(poll_id) {
+        Some(poll) => {
+            poll_table.poll_id().update(Poll { group_id, ..poll });
+            Ok(())
+        }
+        None => Err(fail(E_NOT_FOUND, "Poll not found")),
+    }
+}
+
+// Pinning replaces whichever poll was pinned before; returns the rows to write
+fn pin_changes(polls: Vec<Poll>, poll_id: u64) -> Vec<Poll> {
+    polls.into_iter()
+        .filter(|p| p.poll_id == poll_id || p.is_pinned)
+        .map(|p| Poll {
+            is_pinned: p.poll_id == poll_id,
+            ..p
+        })
+        .collect()
+}
+
+// option_texts are given in option_id order and must cover every option
+#[reducer]
+pub fn add_translation(ctx: &ReducerContext, poll_id: u64, lang: String, question: String, option_texts: Vec<String>) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    require_admin(ctx, "Only admins can add translations")?;
+    
+    let lang = normalize_lang(&lang)?;
+    if ctx.db.poll().poll_id().find(poll_id).is_none() {
+        return Err(fail(E_NOT_FOUND, "Poll not found"));
+    }
+    validate_question(&question)?;
+    for text in option_texts.iter() {
+        validate_option_text(text)?;
+    }
+    
+    let mut options = ctx.db.poll_option().iter()
+        .filter(|o| o.poll_id == poll_id)
+        .collect::<Vec<_>>();
+    options.sort_by_key(|o| o.option_id);
+    if options.len() != option_texts.len() {
+        return Err(fail(E_INVALID_INPUT, format!("Expected {} option texts, got {}", options.len(), option_texts.len())));
+    }
+    
+    // Adding a language again replaces the earlier translation
+    let translation_table = ctx.db.poll_translation();
+    if let Some(existing) = translation_table.poll_id().filter(poll_id).find(|t| t.lang == lang) {
+        translation_table.translation_id().delete(existing.translation_id);
+    }
+    translation_table.insert(PollTranslation {
+        translation_id: 0,
+        poll_id,
+        lang: lang.clone(),
+        question,
+    });
+    
+    let option_translation_table = ctx.db.poll_option_translation();
+    for (option, text) in options.into_iter().zip(option_texts) {
+        if let Some(existing) = option_translation_table.option_id().filter(option.option_id).find(|t| t.lang == lang) {
+            option_translation_table.translation_id().delete(existing.translation_id);
+        }
+        option_translation_table.insert(PollOptionTranslation {
+            translation_id: 0,
+            option_id: option.option_id,
+            lang: lang.clone(),
+            text,
+        });
+    }
+    
+    Ok(())
+}
+
+#[reducer]
+pub fn pin_poll(ctx: &ReducerContext, poll_id: u64) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    require_admin(ctx, "Only admins can pin polls")?;
+    
+    let poll_table = ctx.db.poll();
+    if poll_table.poll_id().find(poll_id).is_none() {
+        return Err(fail(E_NOT_FOUND, "Poll not found"));
+    }
+    
+    for poll in pin_changes(poll_table.iter().collect(), poll_id) {
+        poll_table.poll_id().update(poll);
+    }
+    Ok(())
+}
+
+#[reducer]
+pub fn unpin_poll(ctx: &ReducerContext, poll_id: u64) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    require_admin(ctx, "Only admins can pin polls")?;
+    
+    let poll_table = ctx.db.poll();
+    match poll_table.poll_id().find(poll_id) {
+        Some(poll) => {
+            poll_table.poll_id().update(Poll { is_pinned: false, ..poll });
+            Ok(())
+        }
+        None => Err(fail(E_NOT_FOUND, "Poll not found")),
+    }
+}
+
+#[reducer]
+pub fn publish_poll(ctx: &ReducerContext, poll_id: u64) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    require_admin(ctx, "Only admins can publish polls")?;
+    
+    let poll_table = ctx.db.poll();
+    match poll_table.poll_id().find(poll_id) {
+        Some(poll) if !poll.is_draft => Err(fail(E_CONFLICT, "Poll is already published")),
+        Some(poll) => {
+            poll_table.poll_id().update(Poll { is_draft: false, ..poll });
+            Ok(())
+        }
+        None => Err(fail(E_NOT_FOUND, "Poll not found")),
+    }
+}
+
+#[reducer]
+pub fn set_poll_visibility(ctx: &ReducerContext, poll_id: u64, visible_to_role: Option<String>) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    require_admin(ctx, "Only admins can change poll visibility")?;
+    
+    if let Some(role) = &visible_to_role {
+        if role != "user" && role != "admin" {
+            return Err(fail(E_INVALID_INPUT, "Invalid role. Must be 'user' or 'admin'"));
+        }
+    }
+    
+    let poll_table = ctx.db.poll();
+    match poll_table.poll_id().find(poll_id) {
+        Some(poll) => {
+            poll_table.poll_id().update(Poll { visible_to_role, ..poll });
+            Ok(())
+        }
+        None => Err(fail(E_NOT_FOUND, "Poll not found")),
+    }
+}
+
+// Walks the prerequisite chain; reaching `poll_id` again means a cycle
+fn check_prerequisite_chain(poll_id: u64, requires_poll_id: Option<u64>, find_poll: impl Fn(u64) -> Option<Poll>) -> Result<(), String> {
+    let mut next = requires_poll_id;
+    while let Some(id) = next {
+        if id == poll_id {
+            return Err(fail(E_INVALID_INPUT, "Prerequisite would create a cycle"));
+        }
+        next = match find_poll(id) {
+            Some(p) => p.requires_poll_id,
+            None => return Err(fail(E_NOT_FOUND, "Prerequisite poll not found")),
+        };
+    }
+    Ok(())
+}
+
+fn validate_window(start_micros: i64, end_micros: i64) -> Result<(), String> {
+    if start_micros >= end_micros {
+        return Err(fail(E_INVALID_INPUT, "Voting window must start before it ends"));
+    }
+    Ok(())
+}
+
+#[reducer]
+pub fn voting_window(ctx: &ReducerContext, poll_id: u64, start_micros: i64, end_micros: i64) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    require_admin(ctx, "Only admins can set voting windows")?;
+    
+    validate_window(start_micros, end_micros)?;
+    
+    let poll_table = ctx.db.poll();
+    match poll_table.poll_id().find(poll_id) {
+        Some(poll) => {
+            poll_table.poll_id().update(Poll {
+                window_start: Some(start_micros),
+                window_end: Some(end_micros),
+                ..poll
+            });
+            Ok(())
+        }
+        None => Err(fail(E_NOT_FOUND, "Poll not found")),
+    }
+}
+
+#[reducer]
+pub fn clear_voting_window(ctx: &ReducerContext, poll_id: u64) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    require_admin(ctx, "Only admins can set voting windows")?;
+    
+    let poll_table = ctx.db.poll();
+    match poll_table.poll_id().find(poll_id) {
+        Some(poll) => {
+            poll_table.poll_id().update(Poll {
+                window_start: None,
+                window_end: None,
+                ..poll
+            });
+            Ok(())
+        }
+        None => Err(fail(E_NOT_FOUND, "Poll not found")),
+    }
+}
+
+#[reducer]
+pub fn set_prerequisite(ctx: &ReducerContext, poll_id: u64, requires_poll_id: Option<u64>) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    require_admin(ctx, "Only admins can set prerequisites")?;
+    
+    let poll_table = ctx.db.poll();
+    let poll = match poll_table.poll_id().find(poll_id) {
+        Some(poll) => poll,
+        None => return Err(fail(E_NOT_FOUND, "Poll not found")),
+    };
+    
+    check_prerequisite_chain(poll_id, requires_poll_id, |id| poll_table.poll_id().find(id))?;
+    
+    poll_table.poll_id().update(Poll { requires_poll_id, ..poll });
+    Ok(())
+}
+
+#[reducer]
+pub fn submit_write_in(ctx: &ReducerContext, poll_id: u64, text: String) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    
+    let poll = match ctx.db.poll().poll_id().find(poll_id) {
+        Some(poll) => poll,
+        None => return Err(fail(E_NOT_FOUND, "Poll not found")),
+    };
+    if !poll.allow_write_ins {
+        return Err(fail(E_CONFLICT, "Write-ins are not allowed for this poll"));
+    }
+    
+    let text = text.trim().to_string();
+    if text.is_empty() {
+        return Err(fail(E_INVALID_INPUT, "Write-in cannot be empty"));
+    }
+    if text.chars().count() > MAX_WRITE_IN_LEN {
+        return Err(fail(E_INVALID_INPUT, format!("Write-in exceeds the maximum length of {} characters", MAX_WRITE_IN_LEN)));
+    }
+    validate_plain_text(&text)?;
+    
+    // Reuse an existing option with the same text instead of adding a duplicate
+    let user_id = ctx.sender.to_string();
+    let options = ctx.db.poll_option().iter()
+        .filter(|o| o.poll_id == poll_id)
+        .collect::<Vec<_>>();
+    let option_id = match existing_write_in(&options, &text, &user_id)? {
+        Some(option_id) => option_id,
+        None => {
+            let option = insert_option(ctx, poll_id, OptionInput {
+                text,
+                image_url: None,
+                metadata: None,
+            });
+            let option_id = option.option_id;
+            ctx.db.poll_option().option_id().update(PollOption {
+                added_by: Some(user_id),
+                ..option
+            });
+            option_id
+        }
+    };
+    
+    // Any rejection here rolls back the new option as well
+    cast_vote(ctx, ctx.sender.to_string(), None, poll_id, option_id, None, false)
+}
+
+// Shared by cast_vote and confirm_vote
+fn check_voting_open(ctx: &ReducerContext, poll: &Poll) -> Result<(), String> {
+    voting_open_at(poll, ctx.timestamp.to_micros_since_unix_epoch(), get_settings(ctx).grace_secs)?;
+    if ctx.db.presentation_state().id().find(0).is_some_and(|s| s.state == "paused") {
+        return Err(fail(E_CLOSED, "Session is paused"));
+    }
+    Ok(())
+}
+
+// Governance polls can bar the proposer from voting on their own poll
+fn check_creator_vote(poll: &Poll, user_id: &str) -> Result<(), String> {
+    if !poll.creator_can_vote && poll.created_by.as_deref() == Some(user_id) {
+        return Err(fail(E_FORBIDDEN, "Poll creator cannot vote"));
+    }
+    Ok(())
+}
+
+// Rejects a new voter once max_votes are in. Provisional votes hold a place
+// too, so confirming can't overshoot the cap.
+fn check_capacity(poll: &Poll, votes: &[Vote]) -> Result<(), String> {
+    if let Some(max_votes) = poll.max_votes {
+        let admitted = votes.iter().filter(|v| !v.voided).count() as u64;
+        if admitted >= max_votes as u64 {
+            return Err(fail(E_CLOSED, "Voting is full"));
+        }
+    }
+    Ok(())
+}
+
+// The history row for one vote submission; anonymous polls keep no history
+fn vote_revision_for(poll: &Poll, user_id: String, option_id: u64, changed_at: i64) -> Option<VoteRevision> {
+    if poll.anonymous_reports {
+        return None;
+    }
+    Some(VoteRevision {
+        revision_id: 0,
+        poll_id: poll.poll_id,
+        user_id,
+        option_id,
+        changed_at,
+    })
+}
+
+// Voting and abstaining are exclusive, so whichever came first stands
+fn check_not_abstained(mut abstentions: impl Iterator<Item = Abstention>, user_id: &str) -> Result<(), String> {
+    if abstentions.any(|a| a.user_id == user_id) {
+        return Err(fail(E_CONFLICT, "Already abstained"));
+    }
+    Ok(())
+}
+
+// The log row for a vote turned away because voting is closed, when
+// Settings.log_rejected is on
+fn rejected_attempt(settings: &Settings, user_id: String, poll_id: u64, reason: String, now: i64) -> Option<RejectedVoteAttempt> {
+    if !reason.starts_with(E_CLOSED) || !settings.log_rejected {
+        return None;
+    }
+    Some(RejectedVoteAttempt {
+        attempt_id: 0,
+        user_id,
+        poll_id,
+        reason,
+        attempted_at: now,
+    })
+}
+
+// Grantors a proxy's vote is copied to: those who haven't voted on the poll
+// themselves. A vote the proxy cast for them earlier follows the proxy's
+// choice. `votes` are the poll's votes.
+fn grantors_to_cast(proxies: Vec<Proxy>, votes: &[Vote]) -> Vec<String> {
+    proxies.into_iter()
+        .map(|p| p.grantor_user_id)
+        .filter(|grantor| !votes.iter().any(|v| v.user_id == *grantor && v.cast_by.is_none()))
+        .collect()
+}
+
+#[reducer]
+pub fn submit_vote(ctx: &ReducerContext, poll_id: u64, option_id: u64, source: Option<String>) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    let proxy_id = ctx.sender.to_string();
+    if let Err(e) = cast_vote(ctx, proxy_id.clone(), None, poll_id, option_id, source.clone(), false) {
+        let now = ctx.timestamp.to_micros_since_unix_epoch();
+        // Returning Err would roll the log row back, so a logged attempt ends
+        // the call quietly; no vote is recorded either way
+        if let Some(attempt) = rejected_attempt(&get_settings(ctx), proxy_id, poll_id, e.clone(), now) {
+            log::warn!("Rejected vote from {} on poll {}: {}", attempt.user_id, poll_id, attempt.reason);
+            ctx.db.rejected_vote_attempt().insert(attempt);
+            return Ok(());
+        }
+        return Err(e);
+    }
+    
+    // Apply the same choice, at each grantor's own weight, for everyone who
+    // delegated to the caller. A grantor who can't vote on this poll is skipped
+    // without failing the caller's vote, and one who voted keeps their choice.
+    let proxies = ctx.db.proxy().proxy_user_id().filter(&proxy_id).collect::<Vec<_>>();
+    let votes = ctx.db.vote().poll_id().filter(poll_id).collect::<Vec<_>>();
+    for grantor_user_id in grantors_to_cast(proxies, &votes) {
+        if let Err(e) = cast_vote(ctx, grantor_user_id.clone(), Some(proxy_id.clone()), poll_id, option_id, source.clone(), false) {
+            log::info!("Proxy vote for {} skipped: {}", grantor_user_id, e);
+        }
+    }
+    Ok(())
+}
+
+// Follow the proxy's own delegations; arriving back at the grantor is a cycle
+fn check_delegation_cycle(grantor_user_id: &str, proxy_user_id: &str, proxy_of: impl Fn(&str) -> Option<String>) -> Result<(), String> {
+    let mut next = Some(proxy_user_id.to_string());
+    while let Some(id) = next {
+        if id == grantor_user_id {
+            return Err(fail(E_INVALID_INPUT, "Delegation would create a cycle"));
+        }
+        next = proxy_of(&id);
+    }
+    Ok(())
+}
+
+#[reducer]
+pub fn delegate_vote(ctx: &ReducerContext, proxy_user_id: String) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    
+    let grantor_user_id = ctx.sender.to_string();
+    let user_table = ctx.db.user();
+    if user_table.user_id().find(&grantor_user_id).is_none() {
+        return Err(fail(E_NOT_FOUND, "User not found"));
+    }
+    if user_table.user_id().find(&proxy_user_id).is_none() {
+        return Err(fail(E_NOT_FOUND, "Proxy user not found"));
+    }
+    
+    let proxy_table = ctx.db.proxy();
+    check_delegation_cycle(&grantor_user_id, &proxy_user_id, |id| {
+        proxy_table.grantor_user_id().find(id.to_string()).map(|p| p.proxy_user_id)
+    })?;
+    
+    let row = Proxy {
+        grantor_user_id: grantor_user_id.clone(),
+        proxy_user_id,
+        delegated_at: ctx.timestamp.to_micros_since_unix_epoch(),
+    };
+    if proxy_table.grantor_user_id().find(&grantor_user_id).is_some() {
+        proxy_table.grantor_user_id().update(row);
+    } else {
+        proxy_table.insert(row);
+    }
+    
+    Ok(())
+}
+
+#[reducer]
+pub fn revoke_delegation(ctx: &ReducerContext) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    
+    if !ctx.db.proxy().grantor_user_id().delete(ctx.sender.to_string()) {
+        return Err(fail(E_NOT_FOUND, "No delegation to revoke"));
+    }
+    Ok(())
+}
+
+// Sets how many times a user's votes count, e.g. their shares. Votes already
+// cast keep the weight they were cast with until the user votes again.
+#[reducer]
+pub fn set_vote_weight(ctx: &ReducerContext, target_user_id: String, vote_weight: u32) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    require_admin(ctx, "Only admins can set vote weights")?;
+    
+    if vote_weight == 0 {
+        return Err(fail(E_INVALID_INPUT, "Vote weight must be at least 1"));
+    }
+    
+    let user_table = ctx.db.user();
+    match user_table.user_id().find(&target_user_id) {
+        Some(user) => {
+            user_table.user_id().update(User { vote_weight, ..user });
+            Ok(())
+        }
+        None => Err(fail(E_NOT_FOUND, "User not found")),
+    }
+}
+
+#[reducer]
+pub fn submit_provisional_vote(ctx: &ReducerContext, poll_id: u64, option_id: u64, source: Option<String>) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    cast_vote(ctx, ctx.sender.to_string(), None, poll_id, option_id, source, true)
+}
+
+// The confirmed vote, and how far its option's result moves. A voided vote
+// stays uncounted.
+fn with_confirmed(vote: Vote) -> Result<(Vote, i64), String> {
+    if !vote.provisional {
+        return Err(fail(E_CONFLICT, "Vote is already confirmed"));
+    }
+    let delta = if vote.voided { 0 } else { vote.weight as i64 };
+    Ok((Vote { provisional: false, ..vote }, delta))
+}
+
+// Promotes the caller's provisional vote so it starts counting
+#[reducer]
+pub fn confirm_vote(ctx: &ReducerContext, poll_id: u64) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    
+    let user_id = ctx.sender.to_string();
+    let poll = match ctx.db.poll().poll_id().find(poll_id) {
+        Some(poll) => poll,
+        None => return Err(fail(E_NOT_FOUND, "Poll not found")),
+    };
+    check_voting_open(ctx, &poll)?;
+    
+    let vote_table = ctx.db.vote();
+    let vote = match vote_table.iter().find(|v| v.poll_id == poll_id && v.user_id == user_id) {
+        Some(vote) => vote,
+        None => return Err(fail(E_NOT_FOUND, "No vote to confirm")),
+    };
+    let (vote, delta) = with_confirmed(vote)?;
+    
+    if delta != 0 {
+        adjust_result(ctx, poll_id, vote.option_id, delta);
+    }
+    vote_table.vote_id().update(vote);
+    Ok(())
+}
+
+// Records a vote for user_id, who is the caller or, when cast_by names their
+// proxy, one of the proxy's grantors. Callers run guard_mutation first. Every
+// check happens before the first write, so an Err leaves nothing behind.
+fn cast_vote(ctx: &ReducerContext, user_id: String, cast_by: Option<String>, poll_id: u64, option_id: u64, source: Option<String>, provisional: bool) -> Result<(), String> {
+    validate_source(&source)?;
+    
+    // Check if user exists
+    let user_table = ctx.db.user();
+    let user = match user_table.user_id().find(&user_id) {
+        Some(user) => user,
+        None => return Err(fail(E_NOT_FOUND, "User not found")),
+    };
+    
+    // Check if poll exists and is active. Polls restricted to another
+    // role are reported as missing so their existence isn't leaked.
+    let poll_table = ctx.db.poll();
+    let poll = match poll_table.poll_id().find(poll_id) {
+        Some(poll) if poll_visible_to(&poll, &user) => poll,
+        _ => return Err(fail(E_NOT_FOUND, "Poll not found")),
+    };
+    check_voting_open(ctx, &poll)?;
+    check_creator_vote(&poll, &user_id)?;
+    check_not_abstained(ctx.db.abstention().poll_id().filter(poll_id), &user_id)?;
+    
+    // Check if option exists for this poll
+    resolve_option(ctx, poll_id, option_id)?;
+    
+    // Look for existing vote
+    let vote_table = ctx.db.vote();
+    
+    // Find existing vote among this poll's votes
+    let existing_vote = vote_table.poll_id().filter(poll_id)
+        .find(|v| v.user_id == user_id);
+    
+    if let Some(existing) = existing_vote {
+        let changed = check_vote_change(&poll, &existing, option_id, provisional)?;
+        for (counted_option_id, delta) in result_deltas(Some(&existing), option_id, provisional, user.vote_weight) {
+            adjust_result(ctx, poll_id, counted_option_id, delta);
+        }
+        
+        // Update existing vote
+        let updated_vote = Vote {
+            option_id,
+            voted_at: ctx.timestamp.to_micros_since_unix_epoch(),
+            source,
+            change_count: existing.change_count + u32::from(changed),
+            provisional,
+            weight: user.vote_weight,
+            cast_by,
+            ..existing
+        };
+        
+        vote_table.vote_id().update(updated_vote);
+        extend_checksum(ctx, poll_id, existing.vote_id, option_id);
+    } else {
+        // Only new voters count toward the cap; existing ones may still change
+        if poll.max_votes.is_some() {
+            let votes = vote_table.poll_id().filter(poll_id).collect::<Vec<_>>();
+            check_capacity(&poll, &votes)?;
+        }
+        
+        // Create new vote
+        let vote_id = next_id(ctx, "vote");
+        
+        let vote = Vote {
+            vote_id,
+            poll_id,
+            user_id: user_id.clone(),
+            option_id,
+            voted_at: ctx.timestamp.to_micros_since_unix_epoch(),
+            source,
+            change_count: 0,
+            voided: false,
+            provisional,
+            weight: user.vote_weight,
+            cast_by,
+            is_demo: false,
+        };
+        
+        vote_table.insert(vote);
+        for (counted_option_id, delta) in result_deltas(None, option_id, provisional, user.vote_weight) {
+            adjust_result(ctx, poll_id, counted_option_id, delta);
+        }
+        
+        extend_checksum(ctx, poll_id, vote_id, option_id);
+    }
+    
+    // Record the submission itself, whether it's new or a change
+    if let Some(revision) = vote_revision_for(&poll, user_id, option_id, ctx.timestamp.to_micros_since_unix_epoch()) {
+        ctx.db.vote_revision().insert(revision);
+    }
+    
+    Ok(())
+}
+
+#[reducer]
+pub fn submit_ranking(ctx: &ReducerContext, poll_id: u64, ranking: Vec<u64>) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    
+    let user_id = ctx.sender.to_string();
+    let user = match ctx.db.user().user_id().find(&user_id) {
+        Some(user) => user,
+        None => return Err(fail(E_NOT_FOUND, "User not found")),
+    };
+    
+    let poll = match ctx.db.poll().poll_id().find(poll_id) {
+        Some(poll) if poll_visible_to(&poll, &user) => poll,
+        _ => return Err(fail(E_NOT_FOUND, "Poll not found")),
+    };
+    check_voting_open(ctx, &poll)?;
+    
+    if ranking.is_empty() {
+        return Err(fail(E_INVALID_INPUT, "Ranking cannot be empty"));
+    }
+    for (i, option_id) in ranking.iter().enumerate() {
+        if ranking[..i].contains(option_id) {
+            return Err(fail(E_INVALID_INPUT, "Each option can only be ranked once"));
+        }
+        resolve_option(ctx, poll_id, *option_id)?;
+    }
+    
+    // One ballot per user; resubmitting replaces it
+    let ballot_table = ctx.db.ranked_ballot();
+    let existing = ballot_table.poll_id().filter(poll_id).find(|b| b.user_id == user_id);
+    let submitted_at = ctx.timestamp.to_micros_since_unix_epoch();
+    match existing {
+        Some(ballot) => {
+            ballot_table.ballot_id().update(RankedBallot { ranking, submitted_at, ..ballot });
+        }
+        None => {
+            ballot_table.insert(RankedBallot {
+                ballot_id: 0,
+                poll_id,
+                user_id,
+                ranking,
+                submitted_at,
+            });
+        }
+    }
+    
+    Ok(())
+}
+
+// Choice ballots are stored sorted and deduplicated
+fn normalized_choices(mut option_ids: Vec<u64>) -> Result<Vec<u64>, String> {
+    if option_ids.is_empty() {
+        return Err(fail(E_INVALID_INPUT, "Select at least one option"));
+    }
+    option_ids.sort();
+    option_ids.dedup();
+    Ok(option_ids)
+}
+
+#[reducer]
+pub fn submit_choices(ctx: &ReducerContext, poll_id: u64, option_ids: Vec<u64>) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    
+    let user_id = ctx.sender.to_string();
+    let user = match ctx.db.user().user_id().find(&user_id) {
+        Some(user) => user,
+        None => return Err(fail(E_NOT_FOUND, "User not found")),
+    };
+    let poll = match ctx.db.poll().poll_id().find(poll_id) {
+        Some(poll) if poll_visible_to(&poll, &user) => poll,
+        _ => return Err(fail(E_NOT_FOUND, "Poll not found")),
+    };
+    check_voting_open(ctx, &poll)?;
+    
+    let option_ids = normalized_choices(option_ids)?;
+    for option_id in option_ids.iter() {
+        resolve_option(ctx, poll_id, *option_id)?;
+    }
+    
+    // One ballot per user; resubmitting replaces it unless changes are disabled
+    let ballot_table = ctx.db.choice_ballot();
+    let existing = ballot_table.poll_id().filter(poll_id).find(|b| b.user_id == user_id);
+    let submitted_at = ctx.timestamp.to_micros_since_unix_epoch();
+    match existing {
+        Some(_) if !poll.allow_change => {
+            return Err(fail(E_CONFLICT, "Choices already submitted and changes are disabled"));
+        }
+        Some(ballot) => {
+            ballot_table.ballot_id().update(ChoiceBallot { option_ids, submitted_at, ..ballot });
+        }
+        None => {
+            ballot_table.insert(ChoiceBallot {
+                ballot_id: 0,
+                poll_id,
+                user_id,
+                option_ids,
+                submitted_at,
+            });
+        }
+    }
+    
+    Ok(())
+}
+
+#[reducer]
+pub fn submit_text_response(ctx: &ReducerContext, poll_id: u64, text: String) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    
+    let user_id = ctx.sender.to_string();
+    let user = match ctx.db.user().user_id().find(&user_id) {
+        Some(user) => user,
+        None => return Err(fail(E_NOT_FOUND, "User not found")),
+    };
+    
+    // Polls restricted to another role are reported as missing, as in cast_vote
+    match ctx.db.poll().poll_id().find(poll_id) {
+        Some(poll) if !poll_visible_to(&poll, &user) => return Err(fail(E_NOT_FOUND, "Poll not found")),
+        Some(poll) if !poll.is_active => return Err(fail(E_CLOSED, "Poll is not active")),
+        Some(_) => {}
+        None => return Err(fail(E_NOT_FOUND, "Poll not found")),
+    }
+    
+    let text = text.trim().to_string();
+    if text.is_empty() {
+        return Err(fail(E_INVALID_INPUT, "Response cannot be empty"));
+    }
+    if text.chars().count() > MAX_TEXT_RESPONSE_LEN {
+        return Err(fail(E_INVALID_INPUT, format!("Response exceeds the maximum length of {} characters", MAX_TEXT_RESPONSE_LEN)));
+    }
+    
+    ctx.db.text_response().insert(TextResponse {
+        response_id: next_id(ctx, "text_response"),
+        poll_id,
+        user_id,
+        text,
+        submitted_at: ctx.timestamp.to_micros_since_unix_epoch(),
+        flag_count: 0,
+        hidden: false,
+    });
+    
+    Ok(())
+}
+
+#[reducer]
+pub fn abstain(ctx: &ReducerContext, poll_id: u64, reason: Option<String>) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    
+    let user_id = ctx.sender.to_string();
+    let user = match ctx.db.user().user_id().find(&user_id) {
+        Some(user) => user,
+        None => return Err(fail(E_NOT_FOUND, "User not found")),
+    };
+    
+    // Polls restricted to another role are reported as missing, as in cast_vote
+    match ctx.db.poll().poll_id().find(poll_id) {
+        Some(poll) if !poll_visible_to(&poll, &user) => return Err(fail(E_NOT_FOUND, "Poll not found")),
+        Some(poll) if !poll.is_active => return Err(fail(E_CLOSED, "Poll is not active")),
+        Some(_) => {}
+        None => return Err(fail(E_NOT_FOUND, "Poll not found")),
+    }
+    
+    if ctx.db.vote().iter().any(|v| v.poll_id == poll_id && v.user_id == user_id) {
+        return Err(fail(E_CONFLICT, "Vote already cast"));
+    }
+    let abstention_table = ctx.db.abstention();
+    check_not_abstained(abstention_table.poll_id().filter(poll_id), &user_id)?;
+    
+    let reason = validate_abstain_reason(reason)?;
+    
+    abstention_table.insert(Abstention {
+        abstention_id: 0,
+        poll_id,
+        user_id,
+        reason,
+        abstained_at: ctx.timestamp.to_micros_since_unix_epoch(),
+    });
+    
+    Ok(())
+}
+
+#[reducer]
+pub fn flag_response(ctx: &ReducerContext, response_id: u64) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    
+    let user_id = ctx.sender.to_string();
+    if ctx.db.user().user_id().find(&user_id).is_none() {
+        return Err(fail(E_NOT_FOUND, "User not found"));
+    }
+    
+    let response_table = ctx.db.text_response();
+    let response = match response_table.response_id().find(response_id) {
+        Some(response) => response,
+        None => return Err(fail(E_NOT_FOUND, "Response not found")),
+    };
+    
+    // Each user can flag a response once
+    let flag_table = ctx.db.response_flag();
+    if flag_table.response_id().filter(response_id).any(|f| f.user_id == user_id) {
+        return Err(fail(E_CONFLICT, "You have already flagged this response"));
+    }
+    flag_table.insert(ResponseFlag {
+        flag_id: 0,
+        response_id,
+        user_id,
+    });
+    
+    response_table.response_id().update(with_flag(response));
+    
+    Ok(())
+}
+
+#[reducer]
+pub fn hide_response(ctx: &ReducerContext, response_id: u64) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    require_admin(ctx, "Only admins can hide responses")?;
+    set_response_hidden(ctx, response_id, true)
+}
+
+// Unhiding is an admin review, so it also clears the flags that hid the response
+#[reducer]
+pub fn unhide_response(ctx: &ReducerContext, response_id: u64) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    require_admin(ctx, "Only admins can unhide responses")?;
+    ctx.db.response_flag().response_id().delete(response_id);
+    set_response_hidden(ctx, response_id, false)
+}
+
+#[reducer]
+pub fn show_results(ctx: &ReducerContext) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    
+    // Check if user is admin
+    let user_table = ctx.db.user();
+    if let Some(user) = user_table.user_id().find(ctx.sender.to_string()) {
+        if user.role != "admin" {
+            return Err(fail(E_FORBIDDEN, "Only admins can show results"));
+        }
+    } else {
+        return Err(fail(E_NOT_FOUND, "User not found"));
+    }
+    
+    // Update presentation state
+    let presentation_table = ctx.db.presentation_state();
+    if let Some(old_state) = presentation_table.id().find(0) {
+        let new_state = PresentationState {
+            id: 0,
+            current_poll_id: old_state.current_poll_id,
+            state: "results".to_string(),
+            reveal_at: None,
+        };
+        
+        presentation_table.id().update(new_state);
+        Ok(())
+    } else {
+        Err(fail(E_NOT_FOUND, "Presentation state not found"))
+    }
+}
+
+fn revealing_state(state: PresentationState, now: i64, seconds: u32) -> Result<PresentationState, String> {
+    if seconds == 0 || seconds > MAX_REVEAL_SECS {
+        return Err(fail(E_INVALID_INPUT, format!("Countdown must be between 1 and {} seconds", MAX_REVEAL_SECS)));
+    }
+    if state.state == "ended" {
+        return Err(fail(E_CLOSED, "Session has ended"));
+    }
+    Ok(PresentationState {
+        state: "revealing".to_string(),
+        reveal_at: Some(now + seconds as i64 * 1_000_000),
+        ..state
+    })
+}
+
+// Clients count down to reveal_at; finish_reveal then switches to "results"
+#[reducer]
+pub fn reveal_countdown(ctx: &ReducerContext, seconds: u32) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    require_admin(ctx, "Only admins can start a reveal")?;
+    
+    let presentation_table = ctx.db.presentation_state();
+    let state = match presentation_table.id().find(0) {
+        Some(state) => state,
+        None => return Err(fail(E_NOT_FOUND, "Presentation state not found")),
+    };
+    let state = revealing_state(state, ctx.timestamp.to_micros_since_unix_epoch(), seconds)?;
+    let reveal_at = state.reveal_at.unwrap_or_default();
+    
+    // Starting again replaces a countdown that is already running
+    let schedule_table = ctx.db.reveal_schedule();
+    for row in schedule_table.iter().collect::<Vec<_>>() {
+        schedule_table.delete(row);
+    }
+    schedule_table.insert(RevealSchedule {
+        scheduled_id: 0,
+        scheduled_at: Timestamp::from_micros_since_unix_epoch(reveal_at).into(),
+        reveal_at,
+    });
+    presentation_table.id().update(state);
+    
+    Ok(())
+}
+
+// Skip if the countdown was cancelled or the presentation moved on
+fn revealed_state(state: PresentationState, reveal_at: i64) -> Option<PresentationState> {
+    if state.state != "revealing" || state.reveal_at != Some(reveal_at) {
+        return None;
+    }
+    Some(PresentationState {
+        state: "results".to_string(),
+        reveal_at: None,
+        ..state
+    })
+}
+
+#[reducer]
+pub fn finish_reveal(ctx: &ReducerContext, schedule: RevealSchedule) -> Result<(), String> {
+    if ctx.sender != ctx.identity() {
+        return Err(fail(E_FORBIDDEN, "finish_reveal may only be invoked by the scheduler"));
+    }
+    if get_settings(ctx).sealed {
+        return Ok(());
+    }
+    
+    let presentation_table = ctx.db.presentation_state();
+    if let Some(state) = presentation_table.id().find(0).and_then(|s| revealed_state(s, schedule.reveal_at)) {
+        presentation_table.id().update(state);
+    }
+    
+    Ok(())
+}
+
+// Returns to "voting" if the current poll is still open, otherwise "waiting"
+fn cancelled_reveal(state: PresentationState, poll_open: bool) -> PresentationState {
+    PresentationState {
+        state: if poll_open { "voting" } else { "waiting" }.to_string(),
+        reveal_at: None,
+        ..state
+    }
+}
+
+#[reducer]
+pub fn cancel_reveal(ctx: &ReducerContext) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    require_admin(ctx, "Only admins can cancel a reveal")?;
+    
+    let presentation_table = ctx.db.presentation_state();
+    let state = match presentation_table.id().find(0) {
+        Some(state) if state.state == "revealing" => state,
+        _ => return Err(fail(E_CONFLICT, "No reveal in progress")),
+    };
+    
+    let schedule_table = ctx.db.reveal_schedule();
+    for row in schedule_table.iter().collect::<Vec<_>>() {
+        schedule_table.delete(row);
+    }
+    let poll_open = ctx.db.poll().poll_id().find(state.current_poll_id).is_some_and(|p| p.is_active);
+    presentation_table.id().update(cancelled_reveal(state, poll_open));
+    
+    Ok(())
+}
+
+#[reducer]
+pub fn end_session(ctx: &ReducerContext, close_reason: Option<String>) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    
+    // Check if user is admin
+    let user_table = ctx.db.user();
+    if let Some(user) = user_table.user_id().find(ctx.sender.to_string()) {
+        if user.role != "admin" {
+            return Err(fail(E_FORBIDDEN, "Only admins can end the session"));
+        }
+    } else {
+        return Err(fail(E_NOT_FOUND, "User not found"));
+    }
+    let close_reason = validate_close_reason(close_reason)?;
+    
+    // Update presentation state
+    let presentation_table = ctx.db.presentation_state();
+    if let Some(old_state) = presentation_table.id().find(0) {
+        let new_state = PresentationState {
+            id: 0,
+            current_poll_id: old_state.current_poll_id,
+            state: "ended".to_string(),
+            reveal_at: None,
+        };
+        
+        presentation_table.id().update(new_state);
+        
+        // Deactivate all polls
+        let poll_table = ctx.db.poll();
+        for poll in poll_table.iter() {
+            if poll.is_active {
+                // Update poll to inactive
+                deactivate_poll(ctx, poll, close_reason.clone());
+            }
+        }
+        
+        Ok(())
+    } else {
+        Err(fail(E_NOT_FOUND, "Presentation state not found"))
+    }
+}
+
+// The vote with its voided flag set, and how far its option's result moves
+fn with_voided(vote: Vote, voided: bool) -> Result<(Vote, i64), String> {
+    if vote.voided == voided {
+        return Err(fail(E_CONFLICT, if voided { "Vote is already voided" } else { "Vote is not voided" }));
+    }
+    // Provisional votes were never counted, so there is nothing to move
+    let delta = match (vote.provisional, voided) {
+        (true, _) => 0,
+        (false, true) => -(vote.weight as i64),
+        (false, false) => vote.weight as i64,
+    };
+    Ok((Vote { voided, ..vote }, delta))
+}
+
+fn set_vote_voided(ctx: &ReducerContext, vote_id: u64, voided: bool) -> Result<(), String> {
+    let vote_table = ctx.db.vote();
+    let vote = match vote_table.vote_id().find(vote_id) {
+        Some(vote) => vote,
+        None => return Err(fail(E_NOT_FOUND, "Vote not found")),
+    };
+    let (vote, delta) = with_voided(vote, voided)?;
+    // The vote's result row is keyed by its option, so it has to still line up
+    resolve_option(ctx, vote.poll_id, vote.option_id)?;
+    
+    if delta != 0 {
+        adjust_result(ctx, vote.poll_id, vote.option_id, delta);
+    }
+    vote_table.vote_id().update(vote);
+    Ok(())
+}
+
+#[reducer]
+pub fn void_vote(ctx: &ReducerContext, vote_id: u64) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    require_admin(ctx, "Only admins can void votes")?;
+    set_vote_voided(ctx, vote_id, true)
+}
+
+#[reducer]
+pub fn unvoid_vote(ctx: &ReducerContext, vote_id: u64) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    require_admin(ctx, "Only admins can restore votes")?;
+    set_vote_voided(ctx, vote_id, false)
+}
+
+// Lets a user start over without leaving the session
+#[reducer]
+pub fn reset_user_votes(ctx: &ReducerContext, target_user_id: String) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    require_admin(ctx, "Only admins can reset votes")?;
+    
+    if ctx.db.user().user_id().find(&target_user_id).is_none() {
+        return Err(fail(E_NOT_FOUND, "User not found"));
+    }
+    let removed = clear_user_votes(ctx, &target_user_id);
+    log::info!("Reset {} votes for user {}", removed, target_user_id);
+    
+    Ok(())
+}
+
+// Final, irreversible end of the session: closes every poll, snapshots the
+// results and rejects all later mutations
+#[reducer]
+pub fn seal_session(ctx: &ReducerContext) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    
+    require_superadmin(ctx)?;
+    
+    // The archive keeps whatever checksums the polls have. A poll that fails
+    // verification is flagged in integrity_report rather than papered over.
+    let poll_table = ctx.db.poll();
+    for poll in poll_table.iter() {
+        if !check_integrity(ctx, poll.poll_id).valid {
+            log::warn!("seal_session: poll {} fails verify_integrity", poll.poll_id);
+        }
+    }
+    
+    for poll in poll_table.iter().collect::<Vec<_>>() {
+        if poll.is_active {
+            deactivate_poll(ctx, poll, Some("sealed".to_string()));
+        }
+    }
+    
+    // Rebuild results from the votes so the snapshot can't carry drift
+    let sealed_at = ctx.timestamp.to_micros_since_unix_epoch();
+    let snapshot_table = ctx.db.sealed_result();
+    for poll in poll_table.iter() {
+        recount_results(ctx, poll.poll_id);
+        for (option, vote_count) in option_counts(ctx, poll.poll_id) {
+            snapshot_table.insert(SealedResult {
+                option_id: option.option_id,
+                poll_id: poll.poll_id,
+                question: poll.question.clone(),
+                option_text: option.text,
+                vote_count,
+                sealed_at,
+            });
+        }
+    }
+    
+    let presentation_table = ctx.db.presentation_state();
+    if let Some(state) = presentation_table.id().find(0) {
+        presentation_table.id().update(PresentationState {
+            state: "ended".to_string(),
+            reveal_at: None,
+            ..state
+        });
+    }
+    let session_table = ctx.db.session_state();
+    for session in session_table.iter().collect::<Vec<_>>() {
+        session_table.session_id().update(SessionState {
+            state: "ended".to_string(),
+            ..session
+        });
+    }
+    
+    let settings = get_settings(ctx);
+    save_settings(ctx, Settings {
+        sealed: true,
+        ..settings
+    });
+    
+    Ok(())
+}
+
+// The closed versions of the active polls; closed ones are left alone so
+// running this twice changes nothing
+fn close_active(polls: Vec<Poll>, now: i64) -> Vec<Poll> {
+    polls.into_iter()
+        .filter(|p| p.is_active)
+        .map(|p| closed_poll(p, now, None))
+        .collect()
+}
+
+// Lighter than end_session: closes everything but leaves the session running
+#[reducer]
+pub fn deactivate_all(ctx: &ReducerContext) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    require_admin(ctx, "Only admins can deactivate polls")?;
+    
+    let poll_table = ctx.db.poll();
+    let polls: Vec<Poll> = poll_table.iter().collect();
+    for poll in close_active(polls, ctx.timestamp.to_micros_since_unix_epoch()) {
+        poll_table.poll_id().update(poll);
+    }
+    
+    let presentation_table = ctx.db.presentation_state();
+    if presentation_table.id().find(0).is_some() {
+        presentation_table.id().update(PresentationState {
+            id: 0,
+            current_poll_id: 0,
+            state: "waiting".to_string(),
+            reveal_at: None,
+        });
+    }
+    
+    Ok(())
+}
+
+#[reducer]
+pub fn vote_timeline(ctx: &ReducerContext, poll_id: u64, bucket_secs: u32) -> Result<(), String> {
+    if bucket_secs == 0 {
+        return Err(fail(E_INVALID_INPUT, "Bucket size must be at least one second"));
+    }
+    
+    // Check if poll exists
+    let poll = match ctx.db.poll().poll_id().find(poll_id) {
+        Some(poll) => poll,
+        None => return Err(fail(E_NOT_FOUND, "Poll not found")),
+    };
+    
+    let votes = ctx.db.vote().iter()
+        .filter(|v| v.poll_id == poll_id && !v.voided && !v.provisional)
+        .collect::<Vec<_>>();
+    
+    let rows = timeline_rows(&poll, &votes, bucket_secs)?;
+    
+    // Replace the previous timeline for this poll
+    let timeline_table = ctx.db.timeline();
+    timeline_table.poll_id().delete(poll_id);
+    for row in rows {
+        timeline_table.insert(row);
+    }
+    
+    Ok(())
+}
+
+// Per-option vote counts per bucket, ordered by bucket then option
+fn timeline_rows(poll: &Poll, votes: &[Vote], bucket_secs: u32) -> Result<Vec<Timeline>, String> {
+    let earliest_vote = match votes.iter().map(|v| v.voted_at).min() {
+        Some(t) => t,
+        None => return Ok(Vec::new()), // No votes, empty timeline
+    };
+    let latest_vote = votes.iter().map(|v| v.voted_at).max().unwrap_or(earliest_vote);
+    
+    // Bucket from activation when known, otherwise from the earliest vote.
+    // Votes left over from an earlier activation pull the start back further.
+    let start = poll.activated_at.unwrap_or(earliest_vote).min(earliest_vote);
+    let bucket_micros = bucket_secs as i64 * 1_000_000;
+    let bucket_count = (latest_vote - start) / bucket_micros + 1;
+    if bucket_count > MAX_TIMELINE_BUCKETS {
+        return Err(fail(E_INVALID_INPUT, format!(
+            "Timeline would need {} buckets (max {}); use a larger bucket size",
+            bucket_count, MAX_TIMELINE_BUCKETS
+        )));
+    }
+    
+    // Count votes per (bucket, option)
+    let mut counts: Vec<(u32, u64, u64)> = Vec::new();
+    for vote in votes.iter() {
+        let bucket = ((vote.voted_at - start) / bucket_micros) as u32;
+        match counts.iter_mut().find(|(b, o, _)| *b == bucket && *o == vote.option_id) {
+            Some(entry) => entry.2 += 1,
+            None => counts.push((bucket, vote.option_id, 1)),
+        }
+    }
+    counts.sort();
+    
+    Ok(counts.into_iter()
+        .map(|(bucket, option_id, vote_count)| Timeline {
+            timeline_id: 0,
+            poll_id: poll.poll_id,
+            bucket,
+            bucket_start: start + bucket as i64 * bucket_micros,
+            option_id,
+            vote_count,
+        })
+        .collect())
+}
+
+#[reducer]
+pub fn audit_votes(ctx: &ReducerContext, poll_id: u64) -> Result<(), String> {
+    require_admin(ctx, "Only admins can audit votes")?;
+    
+    if ctx.db.poll().poll_id().find(poll_id).is_none() {
+        return Err(fail(E_NOT_FOUND, "Poll not found"));
+    }
+    
+    // Replace any previous findings for this poll
+    let report_table = ctx.db.anomaly_report();
+    report_table.poll_id().delete(poll_id);
+    
+    let mut votes = ctx.db.vote().iter()
+        .filter(|v| v.poll_id == poll_id)
+        .collect::<Vec<_>>();
+    votes.sort_by_key(|v| v.vote_id);
+    
+    let option_ids = ctx.db.poll_option().iter()
+        .filter(|o| o.poll_id == poll_id)
+        .map(|o| o.option_id)
+        .collect::<Vec<_>>();
+    let user_table = ctx.db.user();
+    let findings = vote_anomalies(&votes, &option_ids, |user_id| user_table.user_id().find(user_id.to_string()).is_some());
+    
+    let now = ctx.timestamp.to_micros_since_unix_epoch();
+    for (vote_id, kind, detail) in findings {
+        report_table.insert(AnomalyReport {
+            report_id: 0,
+            poll_id,
+            vote_id,
+            kind: kind.to_string(),
+            detail,
+            found_at: now,
+        });
+    }
+    
+    Ok(())
+}
+
+// Findings as (vote_id, kind, detail) for one poll's votes in vote_id order
+fn vote_anomalies(votes: &[Vote], option_ids: &[u64], user_present: impl Fn(&str) -> bool) -> Vec<(u64, &'static str, String)> {
+    let mut seen_users: Vec<&str> = Vec::new();
+    let mut findings = Vec::new();
+    
+    for vote in votes.iter() {
+        if !option_ids.contains(&vote.option_id) {
+            findings.push((vote.vote_id, "missing_option", format!("Option {} does not exist in this poll", vote.option_id)));
+        }
+        
+        // The earliest vote per user is treated as the original, later ones as duplicates
+        if seen_users.contains(&vote.user_id.as_str()) {
+            findings.push((vote.vote_id, "duplicate_vote", format!("User {} has more than one vote", vote.user_id)));
+        } else {
+            seen_users.push(&vote.user_id);
+        }
+        
+        // Demo votes come from pseudo-users that never joined
+        if !vote.is_demo && !user_present(&vote.user_id) {
+            findings.push((vote.vote_id, "unknown_user", format!("User {} is no longer present", vote.user_id)));
+        }
+    }
+    
+    findings
+}
+
+// (old, new) poll IDs, and ((old poll_id, old option_id), new) option IDs
+type PollIdMap = Vec<(u64, u64)>;
+type OptionIdMap = Vec<((u64, u64), u64)>;
+
+// Old-to-new ID maps for repair_ids. Polls come in creation order and are
+// numbered from 1; options come ordered by (poll_id, option_id) and are
+// numbered from 1 across all polls, skipping options of missing polls.
+fn renumber_ids(polls: &[Poll], options: &[PollOption]) -> (PollIdMap, OptionIdMap) {
+    let poll_map = polls.iter()
+        .enumerate()
+        .map(|(i, poll)| (poll.poll_id, (i + 1) as u64))
+        .collect::<Vec<_>>();
+    let option_map = options.iter()
+        .filter(|o| poll_map.iter().any(|(old, _)| *old == o.poll_id))
+        .enumerate()
+        .map(|(i, o)| ((o.poll_id, o.option_id), (i + 1) as u64))
+        .collect::<Vec<_>>();
+    (poll_map, option_map)
+}
+
+// Gives votes (in the order they were cast) contiguous IDs and the new poll
+// and option IDs; votes whose poll or option is gone are dropped
+fn renumber_votes(votes: Vec<Vote>, poll_map: &[(u64, u64)], option_map: &[((u64, u64), u64)]) -> Vec<Vote> {
+    let mut renumbered = Vec::new();
+    for vote in votes {
+        let poll_id = poll_map.iter().find(|(o, _)| *o == vote.poll_id).map(|(_, n)| *n);
+        let option_id = option_map.iter().find(|(key, _)| *key == (vote.poll_id, vote.option_id)).map(|(_, n)| *n);
+        let (Some(poll_id), Some(option_id)) = (poll_id, option_id) else {
+            log::warn!("repair_ids: dropping vote {} with unknown poll/option", vote.vote_id);
+            continue;
+        };
+        renumbered.push(Vote {
+            vote_id: renumbered.len() as u64 + 1,
+            poll_id,
+            option_id,
+            ..vote
+        });
+    }
+    renumbered
+}
+
+// Databases upgraded from before owner_id existed have no owner, so the
+// superadmin reducers can't run. The first admin to claim it becomes the owner.
+fn claimed_settings(settings: Settings, user_id: String) -> Result<Settings, String> {
+    if !settings.owner_id.is_empty() {
+        return Err(fail(E_CONFLICT, "Owner is already set"));
+    }
+    Ok(Settings { owner_id: user_id, ..settings })
+}
+
+#[reducer]
+pub fn claim_owner(ctx: &ReducerContext) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    
+    let admin = require_admin(ctx, "Only admins can claim ownership")?;
+    let settings = claimed_settings(get_settings(ctx), admin.user_id)?;
+    log::info!("Owner claimed by {}", settings.owner_id);
+    save_settings(ctx, settings);
+    
+    Ok(())
+}
+
+#[reducer]
+pub fn repair_ids(ctx: &ReducerContext) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    
+    require_superadmin(ctx)?;
+    
+    // New vote IDs mean new chains, which take the rows as they are. So every
+    // existing chain has to verify first.
+    for row in ctx.db.vote_checksum().iter() {
+        if !check_integrity(ctx, row.poll_id).valid {
+            return Err(fail(E_CONFLICT, format!("Poll {} fails verify_integrity; resolve that before repairing IDs", row.poll_id)));
+        }
+    }
+    
+    let poll_table = ctx.db.poll();
+    let option_table = ctx.db.poll_option();
+    let vote_table = ctx.db.vote();
+    
+    let mut polls = poll_table.iter().collect::<Vec<_>>();
+    polls.sort_by_key(|p| (p.created_at, p.poll_id));
+    let mut options = option_table.iter().collect::<Vec<_>>();
+    options.sort_by_key(|o| (o.poll_id, o.option_id));
+    let mut votes = vote_table.iter().collect::<Vec<_>>();
+    votes.sort_by_key(|v| (v.voted_at, v.vote_id));
+    
+    // Clear everything first since new IDs may overlap old ones
+    for poll in polls.iter() {
+        poll_table.poll_id().delete(poll.poll_id);
+    }
+    for option in options.iter() {
+        option_table.option_id().delete(option.option_id);
+    }
+    for vote in votes.iter() {
+        vote_table.vote_id().delete(vote.vote_id);
+    }
+    
+    let (poll_map, option_map) = renumber_ids(&polls, &options);
+    let new_poll_id = |old: u64| poll_map.iter().find(|(o, _)| *o == old).map(|(_, n)| *n);
+    let new_option_id = |old_poll_id: u64, old_option_id: u64| option_map.iter()
+        .find(|(key, _)| *key == (old_poll_id, old_option_id))
+        .map(|(_, n)| *n);
+    
+    let option_count = option_map.len() as u64;
+    for option in options {
+        let (Some(poll_id), Some(option_id)) = (new_poll_id(option.poll_id), new_option_id(option.poll_id, option.option_id)) else {
+            log::warn!("repair_ids: dropping option {} of missing poll {}", option.option_id, option.poll_id);
+            continue;
+        };
+        option_table.insert(PollOption {
+            option_id,
+            poll_id,
+            ..option
+        });
+    }
+    
+    // Inserted after the options so quiz answers can follow their option.
+    // A prerequisite that no longer exists is dropped rather than blocking.
+    for (poll, (_, new_id)) in polls.into_iter().zip(poll_map.iter()) {
+        let correct_option_id = poll.correct_option_id.and_then(|o| new_option_id(poll.poll_id, o));
+        let requires_poll_id = poll.requires_poll_id.and_then(new_poll_id);
+        poll_table.insert(Poll {
+            poll_id: *new_id,
+            correct_option_id,
+            requires_poll_id,
+            ..poll
+        });
+    }
+    
+    let votes = renumber_votes(votes, &poll_map, &option_map);
+    let vote_count = votes.len() as u64;
+    for vote in votes {
+        vote_table.insert(vote);
+    }
+    
+    // Results are keyed by option ID, so rebuild them
+    for row in ctx.db.poll_result().iter().collect::<Vec<_>>() {
+        ctx.db.poll_result().delete(row);
+    }
+    for row in ctx.db.vote_checksum().iter().collect::<Vec<_>>() {
+        ctx.db.vote_checksum().delete(row);
+    }
+    for link in ctx.db.vote_checksum_link().iter().collect::<Vec<_>>() {
+        ctx.db.vote_checksum_link().delete(link);
+    }
+    for (_, poll_id) in poll_map.iter() {
+        recount_results(ctx, *poll_id);
+        let votes = ctx.db.vote().poll_id().filter(*poll_id).collect::<Vec<_>>();
+        store_checksum(ctx, *poll_id, start_chain(ctx, *poll_id, votes));
+    }
+    
+    set_counter(ctx, "poll", poll_map.len() as u64);
+    set_counter(ctx, "poll_option", option_count);
+    set_counter(ctx, "vote", vote_count);
+    
+    // Point the presentation at the renumbered poll
+    let presentation_table = ctx.db.presentation_state();
+    if let Some(state) = presentation_table.id().find(0) {
+        let current_poll_id = new_poll_id(state.current_poll_id).unwrap_or(0);
+        presentation_table.id().update(PresentationState {
+            current_poll_id,
+            ..state
+        });
+    }
+    
+    // Pending scheduled opens follow their poll; a vanished poll's schedule goes
+    let schedule_table = ctx.db.poll_open_schedule();
+    for schedule in schedule_table.iter().collect::<Vec<_>>() {
+        match new_poll_id(schedule.poll_id) {
+            Some(poll_id) => {
+                schedule_table.scheduled_id().update(PollOpenSchedule { poll_id, ..schedule });
+            }
+            None => {
+                schedule_table.scheduled_id().delete(schedule.scheduled_id);
+            }
+        }
+    }
+    
+    // Text responses follow their poll; responses to a vanished poll go
+    let response_table = ctx.db.text_response();
+    for response in response_table.iter().collect::<Vec<_>>() {
+        match new_poll_id(response.poll_id) {
+            Some(poll_id) => {
+                response_table.response_id().update(TextResponse { poll_id, ..response });
+            }
+            None => {
+                response_table.response_id().delete(response.response_id);
+                ctx.db.response_flag().response_id().delete(response.response_id);
+            }
+        }
+    }
+    
+    // Revision history follows the renumbered votes
+    let revision_table = ctx.db.vote_revision();
+    for revision in revision_table.iter().collect::<Vec<_>>() {
+        match (new_poll_id(revision.poll_id), new_option_id(revision.poll_id, revision.option_id)) {
+            (Some(poll_id), Some(option_id)) => {
+                revision_table.revision_id().update(VoteRevision { poll_id, option_id, ..revision });
+            }
+            _ => {
+                revision_table.revision_id().delete(revision.revision_id);
+            }
+        }
+    }
+    
+    // Sessions showing a vanished poll go back to waiting
+    let session_table = ctx.db.session_state();
+    for session in session_table.iter().collect::<Vec<_>>() {
+        let updated = match new_poll_id(session.current_poll_id) {
+            Some(current_poll_id) => SessionState { current_poll_id, ..session },
+            None if session.current_poll_id == 0 => continue,
+            None => SessionState {
+                current_poll_id: 0,
+                state: "waiting".to_string(),
+                ..session
+            },
+        };
+        session_table.session_id().update(updated);
+    }
+    
+    // The queue keys on poll_id uniquely, so clear it before re-adding entries
+    let queue_table = ctx.db.poll_queue();
+    let queued = queue_table.iter().collect::<Vec<_>>();
+    for entry in queued.iter() {
+        queue_table.queue_id().delete(entry.queue_id);
+    }
+    for entry in queued {
+        if let Some(poll_id) = new_poll_id(entry.poll_id) {
+            queue_table.insert(PollQueue { poll_id, ..entry });
+        }
+    }
+    
+    // Abstentions follow their poll
+    let abstention_table = ctx.db.abstention();
+    for abstention in abstention_table.iter().collect::<Vec<_>>() {
+        match new_poll_id(abstention.poll_id) {
+            Some(poll_id) => {
+                abstention_table.abstention_id().update(Abstention { poll_id, ..abstention });
+            }
+            None => {
+                abstention_table.abstention_id().delete(abstention.abstention_id);
+            }
+        }
+    }
+    
+    // Rankings list option IDs; options that are gone drop out of the order
+    let ballot_table = ctx.db.ranked_ballot();
+    for ballot in ballot_table.iter().collect::<Vec<_>>() {
+        match new_poll_id(ballot.poll_id) {
+            Some(poll_id) => {
+                let ranking = ballot.ranking.iter()
+                    .filter_map(|o| new_option_id(ballot.poll_id, *o))
+                    .collect();
+                ballot_table.ballot_id().update(RankedBallot { poll_id, ranking, ..ballot });
+            }
+            None => {
+                ballot_table.ballot_id().delete(ballot.ballot_id);
+            }
+        }
+    }
+    
+    // Choice ballots are remapped like ranked ones and kept sorted
+    let choice_table = ctx.db.choice_ballot();
+    for ballot in choice_table.iter().collect::<Vec<_>>() {
+        match new_poll_id(ballot.poll_id) {
+            Some(poll_id) => {
+                let mut option_ids = ballot.option_ids.iter()
+                    .filter_map(|o| new_option_id(ballot.poll_id, *o))
+                    .collect::<Vec<_>>();
+                option_ids.sort();
+                choice_table.ballot_id().update(ChoiceBallot { poll_id, option_ids, ..ballot });
+            }
+            None => {
+                choice_table.ballot_id().delete(ballot.ballot_id);
+            }
+        }
+    }
+    
+    // Translations follow their poll and option
+    let translation_table = ctx.db.poll_translation();
+    for translation in translation_table.iter().collect::<Vec<_>>() {
+        match new_poll_id(translation.poll_id) {
+            Some(poll_id) => {
+                translation_table.translation_id().update(PollTranslation { poll_id, ..translation });
+            }
+            None => {
+                translation_table.translation_id().delete(translation.translation_id);
+            }
+        }
+    }
+    let option_translation_table = ctx.db.poll_option_translation();
+    for translation in option_translation_table.iter().collect::<Vec<_>>() {
+        let new_id = option_map.iter()
+            .find(|((_, old_option_id), _)| *old_option_id == translation.option_id)
+            .map(|(_, n)| *n);
+        match new_id {
+            Some(option_id) => {
+                option_translation_table.translation_id().update(PollOptionTranslation { option_id, ..translation });
+            }
+            None => {
+                option_translation_table.translation_id().delete(translation.translation_id);
+            }
+        }
+    }
+    
+    // Rejected attempts may name polls that never existed, so unknown ones are kept as-is
+    let attempt_table = ctx.db.rejected_vote_attempt();
+    for attempt in attempt_table.iter().collect::<Vec<_>>() {
+        if let Some(poll_id) = new_poll_id(attempt.poll_id) {
+            attempt_table.attempt_id().update(RejectedVoteAttempt { poll_id, ..attempt });
+        }
+    }
+    
+    // Derived tables reference old IDs; they can be regenerated on demand
+    for row in ctx.db.timeline().iter().collect::<Vec<_>>() {
+        ctx.db.timeline().delete(row);
+    }
+    for row in ctx.db.anomaly_report().iter().collect::<Vec<_>>() {
+        ctx.db.anomaly_report().delete(row);
+    }
+    
+    Ok(())
+}
+
+// Distinct voters across all polls, skipping anonymous ones and demo votes
+fn distinct_voters(votes: &[Vote], is_anonymous: impl Fn(u64) -> bool) -> Vec<String> {
+    let mut user_ids: Vec<String> = Vec::new();
+    for vote in votes.iter().filter(|v| !v.is_demo) {
+        if !is_anonymous(vote.poll_id) && !user_ids.contains(&vote.user_id) {
+            user_ids.push(vote.user_id.clone());
+        }
+    }
+    user_ids.sort();
+    user_ids
+}
+
+#[reducer]
+pub fn unique_voters(ctx: &ReducerContext) -> Result<(), String> {
+    let poll_table = ctx.db.poll();
+    
+    let votes: Vec<Vote> = ctx.db.vote().iter().collect();
+    let user_ids = distinct_voters(&votes, |poll_id| {
+        poll_table.poll_id().find(poll_id).is_some_and(|p| p.anonymous_reports)
+    });
+    
+    let summary = UniqueVoterSummary {
+        id: 0,
+        voter_count: user_ids.len() as u64,
+        user_ids,
+        computed_at: ctx.timestamp.to_micros_since_unix_epoch(),
+    };
+    
+    let summary_table = ctx.db.unique_voter_summary();
+    if summary_table.id().find(0).is_some() {
+        summary_table.id().update(summary);
+    } else {
+        summary_table.insert(summary);
+    }
+    
+    Ok(())
+}
+
+// Open polls visible to `user` that none of `votes` answers, in creation order
+fn unvoted_poll_ids(polls: &[Poll], votes: &[Vote], user: &User) -> Vec<u64> {
+    let mut open = polls.iter()
+        .filter(|p| p.is_active && !p.archived && poll_visible_to(p, user))
+        .filter(|p| !votes.iter().any(|v| v.poll_id == p.poll_id && v.user_id == user.user_id))
+        .collect::<Vec<_>>();
+    open.sort_by_key(|p| (p.created_at, p.poll_id));
+    open.iter().map(|p| p.poll_id).collect()
+}
+
+#[reducer]
+pub fn unvoted_polls(ctx: &ReducerContext) -> Result<(), String> {
+    let user_id = ctx.sender.to_string();
+    let user = match ctx.db.user().user_id().find(&user_id) {
+        Some(user) => user,
+        None => return Err(fail(E_NOT_FOUND, "User not found")),
+    };
+    
+    let polls: Vec<Poll> = ctx.db.poll().iter().collect();
+    let votes: Vec<Vote> = ctx.db.vote().iter().filter(|v| v.user_id == user_id).collect();
+    
+    let row = UnvotedPolls {
+        user_id: user_id.clone(),
+        poll_ids: unvoted_poll_ids(&polls, &votes, &user),
+        computed_at: ctx.timestamp.to_micros_since_unix_epoch(),
+    };
+    
+    let unvoted_table = ctx.db.unvoted_poll_list();
+    if unvoted_table.user_id().find(&user_id).is_some() {
+        unvoted_table.user_id().update(row);
+    } else {
+        unvoted_table.insert(row);
+    }
+    
+    Ok(())
+}
+
+// The winner, tie or no-votes phrase of a results summary
+fn summary_outcome(counts: &[(PollOption, u64)]) -> String {
+    let total: u64 = counts.iter().map(|(_, c)| c).sum();
+    let top = counts.iter().map(|(_, c)| *c).max().unwrap_or(0);
+    if total == 0 {
+        return "No votes recorded".to_string();
+    }
+    
+    let leaders = counts.iter()
+        .filter(|(_, c)| *c == top)
+        .map(|(o, _)| o.text.as_str())
+        .collect::<Vec<_>>();
+    let percent = (top as f64 * 100.0 / total as f64).round();
+    if leaders.len() == 1 {
+        format!("Winner: {} with {}% ({})", leaders[0], percent, plural(top, "vote"))
+    } else {
+        let (last, rest) = leaders.split_last().unwrap();
+        format!("Tie between {} and {} with {}% ({} each)", rest.join(", "), last, percent, plural(top, "vote"))
+    }
+}
+
+// (winner, runner-up, margin), ranked by votes with the lowest option_id
+// first among equals. A lone option wins by all of its votes.
+fn podium(counts: &[(PollOption, u64)]) -> (Option<u64>, Option<u64>, u64) {
+    let total: u64 = counts.iter().map(|(_, c)| c).sum();
+    let mut ranked = counts.iter()
+        .map(|(o, c)| (o.option_id, *c))
+        .collect::<Vec<_>>();
+    ranked.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    let winner = ranked.first().filter(|_| total > 0);
+    let runner_up = ranked.get(1).filter(|_| total > 0);
+    let margin = match (winner, runner_up) {
+        (Some(w), Some(r)) => w.1 - r.1,
+        (Some(w), None) => w.1,
+        _ => 0,
+    };
+    (winner.map(|w| w.0), runner_up.map(|r| r.0), margin)
+}
+
+#[reducer]
+pub fn results_summary(ctx: &ReducerContext, poll_id: u64) -> Result<(), String> {
+    let poll = match ctx.db.poll().poll_id().find(poll_id) {
+        Some(poll) => poll,
+        None => return Err(fail(E_NOT_FOUND, "Poll not found")),
+    };
+    
+    let counts = option_counts(ctx, poll_id);
+    let outcome = summary_outcome(&counts);
+    let (winner_option_id, runner_up_option_id, margin) = podium(&counts);
+    
+    let row = ResultsSummary {
+        poll_id,
+        summary: format!("Question: {} — {}", poll.question, outcome),
+        winner_option_id,
+        runner_up_option_id,
+        margin,
+        computed_at: ctx.timestamp.to_micros_since_unix_epoch(),
+    };
+    
+    let summary_table = ctx.db.results_summary_text();
+    if summary_table.poll_id().find(poll_id).is_some() {
+        summary_table.poll_id().update(row);
+    } else {
+        summary_table.insert(row);
+    }
+    
+    Ok(())
+}
+
+fn check_ballot_access(poll: &Poll, user: &User) -> Result<(), String> {
+    // Polls with anonymous reports never list ballots, not even to admins
+    if poll.anonymous_reports {
+        return Err(fail(E_CONFLICT, "Ballots of anonymous polls are not available"));
+    }
+    if !poll.public_ballots && user.role != "admin" {
+        return Err(fail(E_FORBIDDEN, "Only admins can view ballots for this poll"));
+    }
+    Ok(())
+}
+
+#[reducer]
+pub fn poll_ballots(ctx: &ReducerContext, poll_id: u64) -> Result<(), String> {
+    let user = match ctx.db.user().user_id().find(ctx.sender.to_string()) {
+        Some(user) => user,
+        None => return Err(fail(E_NOT_FOUND, "User not found")),
+    };
+    
+    let poll = match ctx.db.poll().poll_id().find(poll_id) {
+        Some(poll) => poll,
+        None => return Err(fail(E_NOT_FOUND, "Poll not found")),
+    };
+    
+    check_ballot_access(&poll, &user)?;
+    
+    ctx.db.poll_ballot().poll_id().delete(poll_id);
+    ctx.db.private_poll_ballot().poll_id().delete(poll_id);
+    
+    let option_table = ctx.db.poll_option();
+    let mut votes = ctx.db.vote().iter()
+        .filter(|v| v.poll_id == poll_id && !v.is_demo)
+        .collect::<Vec<_>>();
+    votes.sort_by_key(|v| v.voted_at);
+    
+    for vote in votes {
+        let option_text = option_table.option_id().find(vote.option_id)
+            .map(|o| o.text)
+            .unwrap_or_default();
+        let ballot = PollBallot {
+            ballot_id: 0,
+            poll_id,
+            user_id: vote.user_id,
+            option_text,
+        };
+        if poll.public_ballots {
+            ctx.db.poll_ballot().insert(ballot);
+        } else {
+            ctx.db.private_poll_ballot().insert(ballot);
+        }
+    }
+    
+    Ok(())
+}
+
+// Side-by-side percentages. Options are matched by text; unmatched ones get a
+// row of their own.
+fn comparison_rows(counts_a: &[(PollOption, u64)], counts_b: &[(PollOption, u64)]) -> Vec<(String, Option<f64>, Option<f64>)> {
+    let total_a: u64 = counts_a.iter().map(|(_, c)| c).sum();
+    let total_b: u64 = counts_b.iter().map(|(_, c)| c).sum();
+    
+    let mut rows: Vec<(String, Option<f64>, Option<f64>)> = Vec::new();
+    for (option, count) in counts_a.iter() {
+        rows.push((option.text.clone(), Some(percent(*count, total_a)), None));
+    }
+    for (option, count) in counts_b.iter() {
+        let key = normalize_text(&option.text);
+        match rows.iter_mut().find(|(text, _, b)| b.is_none() && normalize_text(text) == key) {
+            Some(row) => row.2 = Some(percent(*count, total_b)),
+            None => rows.push((option.text.clone(), None, Some(percent(*count, total_b)))),
+        }
+    }
+    rows
+}
+
+#[reducer]
+pub fn compare_polls(ctx: &ReducerContext, poll_a: u64, poll_b: u64) -> Result<(), String> {
+    if poll_a == poll_b {
+        return Err(fail(E_INVALID_INPUT, "Cannot compare a poll with itself"));
+    }
+    if ctx.db.poll().poll_id().find(poll_a).is_none() || ctx.db.poll().poll_id().find(poll_b).is_none() {
+        return Err(fail(E_NOT_FOUND, "Poll not found"));
+    }
+    
+    let rows = comparison_rows(&option_counts(ctx, poll_a), &option_counts(ctx, poll_b));
+    
+    let comparison_table = ctx.db.comparison();
+    comparison_table.by_polls().delete((poll_a, poll_b));
+    for (option_text, percent_a, percent_b) in rows {
+        comparison_table.insert(Comparison {
+            comparison_id: 0,
+            poll_a,
+            poll_b,
+            option_text,
+            percent_a,
+            percent_b,
+        });
+    }
+    
+    Ok(())
+}
+
+// Connected users minus voters. Voters who have since disconnected can push
+// this below zero, so it is clamped.
+fn non_voter_count(counts: &[(PollOption, u64)], connected: u64) -> u64 {
+    let voters: u64 = counts.iter().map(|(_, c)| c).sum();
+    connected.saturating_sub(voters)
+}
+
+#[reducer]
+pub fn participation_breakdown(ctx: &ReducerContext, poll_id: u64) -> Result<(), String> {
+    if ctx.db.poll().poll_id().find(poll_id).is_none() {
+        return Err(fail(E_NOT_FOUND, "Poll not found"));
+    }
+    
+    let counts = option_counts(ctx, poll_id);
+    let connected = ctx.db.user().iter().filter(|u| u.connected).count() as u64;
+    let did_not_vote = non_voter_count(&counts, connected);
+    
+    let row_table = ctx.db.participation_row();
+    row_table.poll_id().delete(poll_id);
+    for (option, count) in counts {
+        row_table.insert(ParticipationRow {
+            row_id: 0,
+            poll_id,
+            option_id: Some(option.option_id),
+            label: option.text,
+            count,
+        });
+    }
+    row_table.insert(ParticipationRow {
+        row_id: 0,
+        poll_id,
+        option_id: None,
+        label: "did not vote".to_string(),
+        count: did_not_vote,
+    });
+    
+    Ok(())
+}
+
+#[reducer]
+pub fn verify_integrity(ctx: &ReducerContext, poll_id: u64) -> Result<(), String> {
+    require_admin(ctx, "Only admins can verify vote integrity")?;
+    
+    if ctx.db.poll().poll_id().find(poll_id).is_none() {
+        return Err(fail(E_NOT_FOUND, "Poll not found"));
+    }
+    
+    // A mismatch means votes were changed outside the reducers
+    check_integrity(ctx, poll_id);
+    
+    Ok(())
+}
+
+// Everything a client needs to render the live screen; `poll` is None when
+// nothing visible is being presented
+fn live_view(user_id: String, state: PresentationState, poll: Option<Poll>, counts: Vec<(PollOption, u64)>, now: i64) -> CurrentPollView {
+    CurrentPollView {
+        user_id,
+        state: state.state,
+        poll_id: poll.as_ref().map(|p| p.poll_id),
+        is_active: poll.as_ref().is_some_and(|p| p.is_active),
+        question: poll.map(|p| p.question),
+        options: counts.into_iter()
+            .map(|(o, vote_count)| OptionTally {
+                option_id: o.option_id,
+                text: o.text,
+                image_url: o.image_url,
+                vote_count,
+            })
+            .collect(),
+        computed_at: now,
+    }
+}
+
+#[reducer]
+pub fn current_poll(ctx: &ReducerContext) -> Result<(), String> {
+    let user_id = ctx.sender.to_string();
+    let user = match ctx.db.user().user_id().find(&user_id) {
+        Some(user) => user,
+        None => return Err(fail(E_NOT_FOUND, "User not found")),
+    };
+    
+    heal_presentation_state(ctx);
+    let state = match ctx.db.presentation_state().id().find(0) {
+        Some(state) => state,
+        None => return Err(fail(E_NOT_FOUND, "Presentation state not found")),
+    };
+    
+    let poll = ctx.db.poll().poll_id().find(state.current_poll_id)
+        .filter(|p| poll_visible_to(p, &user));
+    let counts = match &poll {
+        Some(poll) => option_counts(ctx, poll.poll_id),
+        None => Vec::new(),
+    };
+    let view = live_view(user_id.clone(), state, poll, counts, ctx.timestamp.to_micros_since_unix_epoch());
+    
+    let view_table = ctx.db.current_poll_view();
+    if view_table.user_id().find(&user_id).is_some() {
+        view_table.user_id().update(view);
+    } else {
+        view_table.insert(view);
+    }
+    
+    Ok(())
+}
+
+// Shannon entropy of the vote distribution and the 0-1 agreement score
+// derived from it; agreement is None without votes
+fn consensus_scores(counts: &[u64]) -> (f64, Option<f64>) {
+    let total_votes: u64 = counts.iter().sum();
+    let entropy_bits: f64 = counts.iter()
+        .filter(|c| **c > 0)
+        .map(|c| {
+            let p = *c as f64 / total_votes as f64;
+            -p * p.log2()
+        })
+        .sum();
+    
+    // Normalize against the maximum entropy for this many options
+    let max_entropy = (counts.len() as f64).log2();
+    let agreement = if total_votes == 0 {
+        None
+    } else if max_entropy > 0.0 {
+        Some(1.0 - entropy_bits / max_entropy)
+    } else {
+        Some(1.0) // A single option is always unanimous
+    };
+    (entropy_bits, agreement)
+}
+
+#[reducer]
+pub fn consensus_metric(ctx: &ReducerContext, poll_id: u64) -> Result<(), String> {
+    if ctx.db.poll().poll_id().find(poll_id).is_none() {
+        return Err(fail(E_NOT_FOUND, "Poll not found"));
+    }
+    
+    let counts = option_counts(ctx, poll_id).into_iter()
+        .map(|(_, c)| c)
+        .collect::<Vec<_>>();
+    let total_votes: u64 = counts.iter().sum();
+    let (entropy_bits, agreement) = consensus_scores(&counts);
+    
+    let row = Consensus {
+        poll_id,
+        total_votes,
+        entropy_bits,
+        agreement,
+        computed_at: ctx.timestamp.to_micros_since_unix_epoch(),
+    };
+    
+    let consensus_table = ctx.db.consensus();
+    if consensus_table.poll_id().find(poll_id).is_some() {
+        consensus_table.poll_id().update(row);
+    } else {
+        consensus_table.insert(row);
+    }
+    
+    Ok(())
+}
+
+// Counted votes per (source, option), sorted
+fn source_counts(votes: &[Vote]) -> Vec<(String, u64, u64)> {
+    let mut counts: Vec<(String, u64, u64)> = Vec::new();
+    for vote in votes.iter().filter(|v| !v.voided && !v.provisional) {
+        let source = vote.source.clone().unwrap_or_else(|| "unknown".to_string());
+        match counts.iter_mut().find(|(s, o, _)| *s == source && *o == vote.option_id) {
+            Some(entry) => entry.2 += 1,
+            None => counts.push((source, vote.option_id, 1)),
+        }
+    }
+    counts.sort();
+    counts
+}
+
+#[reducer]
+pub fn results_by_source(ctx: &ReducerContext, poll_id: u64) -> Result<(), String> {
+    if ctx.db.poll().poll_id().find(poll_id).is_none() {
+        return Err(fail(E_NOT_FOUND, "Poll not found"));
+    }
+    
+    let votes = ctx.db.vote().iter()
+        .filter(|v| v.poll_id == poll_id)
+        .collect::<Vec<_>>();
+    let counts = source_counts(&votes);
+    
+    let source_table = ctx.db.source_result();
+    source_table.poll_id().delete(poll_id);
+    for (source, option_id, vote_count) in counts {
+        source_table.insert(SourceResult {
+            row_id: 0,
+            poll_id,
+            source,
+            option_id,
+            vote_count,
+        });
+    }
+    
+    Ok(())
+}
+
+// Rebuilds PollResult from the Vote table. Safe to run at any time to heal drift.
+#[reducer]
+pub fn reconcile_results(ctx: &ReducerContext, poll_id: u64) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    require_admin(ctx, "Only admins can reconcile results")?;
+    
+    if ctx.db.poll().poll_id().find(poll_id).is_none() {
+        return Err(fail(E_NOT_FOUND, "Poll not found"));
+    }
+    
+    recount_results(ctx, poll_id);
+    Ok(())
+}
+
+// Users per DURATION_BUCKETS entry by how long they have been connected
+fn duration_counts(users: &[User], now: i64) -> [u64; DURATION_BUCKETS.len()] {
+    let mut counts = [0u64; DURATION_BUCKETS.len()];
+    for user in users {
+        // Still-connected users count up to now
+        let end = if user.connected { now } else { user.last_seen };
+        let secs = (end - user.connected_at).max(0) as u64 / 1_000_000;
+        let bucket = DURATION_BUCKETS.iter()
+            .rposition(|(min_secs, _)| secs >= *min_secs)
+            .unwrap_or(0);
+        counts[bucket] += 1;
+    }
+    counts
+}
+
+#[reducer]
+pub fn session_durations(ctx: &ReducerContext) -> Result<(), String> {
+    let users: Vec<User> = ctx.db.user().iter().collect();
+    let counts = duration_counts(&users, ctx.timestamp.to_micros_since_unix_epoch());
+    
+    let bucket_table = ctx.db.duration_bucket();
+    for (i, (min_secs, label)) in DURATION_BUCKETS.iter().enumerate() {
+        let row = DurationBucket {
+            bucket: i as u8,
+            label: label.to_string(),
+            min_secs: *min_secs,
+            user_count: counts[i],
+        };
+        if bucket_table.bucket().find(i as u8).is_some() {
+            bucket_table.bucket().update(row);
+        } else {
+            bucket_table.insert(row);
+        }
+    }
+    
+    Ok(())
+}
+
+// Median time-to-vote and the fastest correct voter since `activated_at`
+fn timing_stat(poll: &Poll, activated_at: i64, votes: &[Vote], now: i64) -> TimingStat {
+    // Votes left over from an earlier activation would have negative deltas
+    let mut timed = votes.iter()
+        .filter(|v| v.voted_at >= activated_at && !v.is_demo)
+        .map(|v| (v.voted_at - activated_at, v))
+        .collect::<Vec<_>>();
+    timed.sort_by_key(|(delta, v)| (*delta, v.vote_id));
+    
+    let median_micros = match timed.len() {
+        0 => None,
+        n if n % 2 == 1 => Some(timed[n / 2].0),
+        n => Some((timed[n / 2 - 1].0 + timed[n / 2].0) / 2),
+    };
+    
+    let fastest = match poll.correct_option_id {
+        Some(correct) if !poll.anonymous_reports => timed.iter().find(|(_, v)| v.option_id == correct),
+        _ => None,
+    };
+    
+    TimingStat {
+        poll_id: poll.poll_id,
+        timed_votes: timed.len() as u64,
+        median_micros,
+        fastest_user_id: fastest.map(|(_, v)| v.user_id.clone()),
+        fastest_micros: fastest.map(|(delta, _)| *delta),
+        computed_at: now,
+    }
+}
+
+#[reducer]
+pub fn timing_stats(ctx: &ReducerContext, poll_id: u64) -> Result<(), String> {
+    let poll = match ctx.db.poll().poll_id().find(poll_id) {
+        Some(poll) => poll,
+        None => return Err(fail(E_NOT_FOUND, "Poll not found")),
+    };
+    let activated_at = match poll.activated_at {
+        Some(t) => t,
+        None => return Err(fail(E_CONFLICT, "Poll has not been activated")),
+    };
+    
+    let votes = ctx.db.vote().iter()
+        .filter(|v| v.poll_id == poll_id)
+        .collect::<Vec<_>>();
+    let row = timing_stat(&poll, activated_at, &votes, ctx.timestamp.to_micros_since_unix_epoch());
+    
+    let stat_table = ctx.db.timing_stat();
+    if stat_table.poll_id().find(poll_id).is_some() {
+        stat_table.poll_id().update(row);
+    } else {
+        stat_table.insert(row);
+    }
+    
+    Ok(())
+}
+
+#[reducer]
+pub fn vote_history_for(ctx: &ReducerContext, poll_id: u64, user_id: String) -> Result<(), String> {
+    require_admin(ctx, "Only admins can view vote history")?;
+    
+    match ctx.db.poll().poll_id().find(poll_id) {
+        Some(poll) if poll.anonymous_reports => return Err(fail(E_CONFLICT, "Anonymous polls have no vote history")),
+        Some(_) => {}
+        None => return Err(fail(E_NOT_FOUND, "Poll not found")),
+    }
+    
+    let requested_by = ctx.sender.to_string();
+    let history_table = ctx.db.vote_history_entry();
+    history_table.requested_by().delete(&requested_by);
+    
+    let mut revisions = ctx.db.vote_revision().poll_id().filter(poll_id)
+        .filter(|r| r.user_id == user_id)
+        .collect::<Vec<_>>();
+    revisions.sort_by_key(|r| r.revision_id);
+    for revision in revisions {
+        history_table.insert(VoteHistoryEntry {
+            entry_id: 0,
+            requested_by: requested_by.clone(),
+            revision_id: revision.revision_id,
+            poll_id,
+            user_id: revision.user_id,
+            option_id: revision.option_id,
+            changed_at: revision.changed_at,
+        });
+    }
+    
+    Ok(())
+}
+
+// The non-anonymous polls to use as columns, oldest first. An empty
+// `poll_ids` selects all of them; a repeated id is only one column.
+fn matrix_polls(polls: impl Iterator<Item = Poll>, mut poll_ids: Vec<u64>) -> Result<Vec<Poll>, String> {
+    poll_ids.sort_unstable();
+    poll_ids.dedup();
+    let mut polls = polls
+        .filter(|p| !p.anonymous_reports)
+        .filter(|p| poll_ids.is_empty() || poll_ids.contains(&p.poll_id))
+        .collect::<Vec<_>>();
+    if polls.len() < poll_ids.len() {
+        return Err(fail(E_NOT_FOUND, "Poll not found or anonymous"));
+    }
+    polls.sort_by_key(|p| (p.created_at, p.poll_id));
+    Ok(polls)
+}
+
+// One row per user (current users plus anyone who voted) with the option
+// chosen in each column, or None
+fn matrix_rows(columns: &[u64], votes: &[Vote], mut user_ids: Vec<String>) -> Result<Vec<AnswerMatrixRow>, String> {
+    let votes = votes.iter().filter(|v| !v.is_demo).collect::<Vec<_>>();
+    for vote in votes.iter() {
+        if !user_ids.contains(&vote.user_id) {
+            user_ids.push(vote.user_id.clone());
+        }
+    }
+    user_ids.sort();
+    
+    if user_ids.len() * columns.len() > MAX_MATRIX_CELLS {
+        return Err(fail(E_INVALID_INPUT, format!("Matrix exceeds {} cells; pass a subset of poll ids", MAX_MATRIX_CELLS)));
+    }
+    
+    Ok(user_ids.into_iter()
+        .map(|user_id| {
+            let option_ids = columns.iter()
+                .map(|poll_id| {
+                    votes.iter()
+                        .find(|v| v.poll_id == *poll_id && v.user_id == user_id)
+                        .map(|v| v.option_id)
+                })
+                .collect();
+            AnswerMatrixRow { user_id, option_ids }
+        })
+        .collect())
+}
+
+// Builds a users x polls matrix of chosen options. Pass an empty list for every
+// non-anonymous poll, or a subset of poll ids to stay under MAX_MATRIX_CELLS.
+#[reducer]
+pub fn answer_matrix(ctx: &ReducerContext, poll_ids: Vec<u64>) -> Result<(), String> {
+    require_admin(ctx, "Only admins can export the answer matrix")?;
+    
+    let polls = matrix_polls(ctx.db.poll().iter(), poll_ids)?;
+    let columns = polls.iter().map(|p| p.poll_id).collect::<Vec<_>>();
+    
+    let votes = ctx.db.vote().iter()
+        .filter(|v| columns.contains(&v.poll_id))
+        .collect::<Vec<_>>();
+    let user_ids = ctx.db.user().iter().map(|u| u.user_id).collect::<Vec<_>>();
+    let rows = matrix_rows(&columns, &votes, user_ids)?;
+    
+    let row_table = ctx.db.answer_matrix_row();
+    for row in row_table.iter().collect::<Vec<_>>() {
+        row_table.delete(row);
+    }
+    for row in rows {
+        row_table.insert(row);
+    }
+    
+    let header = AnswerMatrixHeader {
+        id: 0,
+        poll_ids: columns,
+        computed_at: ctx.timestamp.to_micros_since_unix_epoch(),
+    };
+    let header_table = ctx.db.answer_matrix_header();
+    if header_table.id().find(0).is_some() {
+        header_table.id().update(header);
+    } else {
+        header_table.insert(header);
+    }
+    
+    Ok(())
+}
+
+// A pruning candidate: inactive, at least `min_age_secs` old and without votes
+fn stale_entry(poll: Poll, vote_count: u64, now: i64, min_age_secs: u64) -> Option<StalePoll> {
+    let age_secs = (now - poll.created_at).max(0) as u64 / 1_000_000;
+    if poll.is_active || age_secs < min_age_secs || vote_count > 0 {
+        return None;
+    }
+    Some(StalePoll {
+        poll_id: poll.poll_id,
+        question: poll.question,
+        age_secs,
+        vote_count,
+    })
+}
+
+// Lists inactive polls older than `min_age_secs` that never got a vote
+#[reducer]
+pub fn stale_polls(ctx: &ReducerContext, min_age_secs: u64) -> Result<(), String> {
+    require_admin(ctx, "Only admins can list stale polls")?;
+    
+    let now = ctx.timestamp.to_micros_since_unix_epoch();
+    let stale_table = ctx.db.stale_poll();
+    for row in stale_table.iter().collect::<Vec<_>>() {
+        stale_table.delete(row);
+    }
+    
+    for poll in ctx.db.poll().iter() {
+        let vote_count: u64 = ctx.db.poll_result().poll_id().filter(poll.poll_id)
+            .map(|r| r.vote_count)
+            .sum();
+        if let Some(row) = stale_entry(poll, vote_count, now, min_age_secs) {
+            stale_table.insert(row);
+        }
+    }
+    
+    Ok(())
+}
+
+// Steps `stored` up to SCHEMA_VERSION; None when it is already current
+fn migrated_version(stored: u32) -> Result<Option<u32>, String> {
+    if stored > SCHEMA_VERSION {
+        return Err(fail(E_CONFLICT, format!("Stored schema version {} is newer than this module ({})", stored, SCHEMA_VERSION)));
+    }
+    if stored == SCHEMA_VERSION {
+        return Ok(None);
+    }
+    
+    let mut version = stored;
+    while version < SCHEMA_VERSION {
+        match version {
+            // 0 -> 1: introduces the meta table itself; nothing to convert
+            0 => {}
+            _ => return Err(fail(E_CONFLICT, format!("No migration from schema version {}", version))),
+        }
+        version += 1;
+        log::info!("Migrated schema to version {}", version);
+    }
+    Ok(Some(version))
+}
+
+fn meta_row(schema_version: u32, now: i64) -> Meta {
+    Meta {
+        id: 0,
+        schema_version,
+        migrated_at: now,
+    }
+}
+
+// Brings stored data up to SCHEMA_VERSION one step at a time. Databases from
+// before the meta table existed count as version 0. Safe to run repeatedly.
+#[reducer]
+pub fn migrate_if_needed(ctx: &ReducerContext) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    
+    require_superadmin(ctx)?;
+    
+    let meta_table = ctx.db.meta();
+    let existing = meta_table.id().find(0);
+    let stored = existing.as_ref().map_or(0, |m| m.schema_version);
+    let version = match migrated_version(stored)? {
+        Some(version) => version,
+        None => return Ok(()),
+    };
+    
+    let row = meta_row(version, ctx.timestamp.to_micros_since_unix_epoch());
+    if existing.is_some() {
+        meta_table.id().update(row);
+    } else {
+        meta_table.insert(row);
+    }
+    
+    Ok(())
+}
+
+// One-time upgrade from the single global presentation_state to one state row
+// per session. Sessions that already have a row are left untouched.
+#[reducer]
+pub fn migrate_to_sessions(ctx: &ReducerContext) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    
+    require_superadmin(ctx)?;
+    
+    let global = match ctx.db.presentation_state().id().find(0) {
+        Some(state) => state,
+        None => return Err(fail(E_NOT_FOUND, "Presentation state not found")),
+    };
+    
+    let session_table = ctx.db.session_state();
+    let users: Vec<User> = ctx.db.user().iter().collect();
+    for session in seeded_sessions(&global, &users) {
+        if session_table.session_id().find(&session.session_id).is_none() {
+            session_table.insert(session);
+        }
+    }
+    
+    Ok(())
+}
+
+// One state row per session the users are in, copied from the global state
+fn seeded_sessions(global: &PresentationState, users: &[User]) -> Vec<SessionState> {
+    let mut sessions: Vec<SessionState> = Vec::new();
+    for user in users {
+        if !sessions.iter().any(|s| s.session_id == user.session_id) {
+            sessions.push(SessionState {
+                session_id: user.session_id.clone(),
+                current_poll_id: global.current_poll_id,
+                state: global.state.clone(),
+            });
+        }
+    }
+    sessions
+}
+
+// (proportion, lower, upper) for one option, all None without votes
+fn wald_interval(count: u64, total: u64) -> (Option<f64>, Option<f64>, Option<f64>) {
+    if total == 0 {
+        return (None, None, None);
+    }
+    let p = count as f64 / total as f64;
+    if total >= MIN_CONFIDENCE_SAMPLE {
+        let margin = CONFIDENCE_Z * (p * (1.0 - p) / total as f64).sqrt();
+        (Some(p), Some((p - margin).max(0.0)), Some((p + margin).min(1.0)))
+    } else {
+        (Some(p), Some(0.0), Some(1.0))
+    }
+}
+
+// 95% Wald interval per option. Small samples get the uninformative [0, 1]
+// interval since the normal approximation breaks down there.
+#[reducer]
+pub fn result_confidence(ctx: &ReducerContext, poll_id: u64) -> Result<(), String> {
+    if ctx.db.poll().poll_id().find(poll_id).is_none() {
+        return Err(fail(E_NOT_FOUND, "Poll not found"));
+    }
+    
+    let counts = option_counts(ctx, poll_id);
+    let total: u64 = counts.iter().map(|(_, c)| c).sum();
+    let reliable = total >= MIN_CONFIDENCE_SAMPLE;
+    
+    let interval_table = ctx.db.confidence_interval();
+    interval_table.poll_id().delete(poll_id);
+    for (option, count) in counts {
+        let (proportion, lower, upper) = wald_interval(count, total);
+        interval_table.insert(ConfidenceInterval {
+            row_id: 0,
+            poll_id,
+            option_id: option.option_id,
+            proportion,
+            lower,
+            upper,
+            reliable,
+        });
+    }
+    
+    Ok(())
+}
+
+// The non-archived polls in creation order, reduced to questions and options
+fn template_polls(mut polls: Vec<Poll>, options: &[PollOption]) -> Vec<TemplatePoll> {
+    polls.retain(|p| !p.archived);
+    polls.sort_by_key(|p| (p.created_at, p.poll_id));
+    polls.into_iter()
+        .map(|poll| {
+            let mut poll_options = options.iter()
+                .filter(|o| o.poll_id == poll.poll_id)
+                .collect::<Vec<_>>();
+            poll_options.sort_by_key(|o| o.option_id);
+            TemplatePoll {
+                question: poll.question,
+                options: poll_options.into_iter()
+                    .map(|o| OptionInput {
+                        text: o.text.clone(),
+                        image_url: o.image_url.clone(),
+                        metadata: o.metadata.clone(),
+                    })
+                    .collect(),
+            }
+        })
+        .collect()
+}
+
+// Validate every poll first so a bad entry can't leave the set half-created
+fn validate_template(template: &PollTemplate) -> Result<(), String> {
+    for poll in &template.polls {
+        validate_question(&poll.question)?;
+        validate_options(&poll.options)?;
+    }
+    Ok(())
+}
+
+// Saves every non-archived poll (questions and options only) under `name`,
+// replacing any template with the same name
+#[reducer]
+pub fn save_template(ctx: &ReducerContext, name: String) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    require_admin(ctx, "Only admins can save templates")?;
+    
+    let name = name.trim().to_string();
+    validate_template_name(&name)?;
+    
+    let polls: Vec<Poll> = ctx.db.poll().iter().collect();
+    let options: Vec<PollOption> = ctx.db.poll_option().iter().collect();
+    let polls = template_polls(polls, &options);
+    if polls.is_empty() {
+        return Err(fail(E_CONFLICT, "There are no polls to save"));
+    }
+    
+    let template = PollTemplate {
+        name: name.clone(),
+        polls,
+        saved_at: ctx.timestamp.to_micros_since_unix_epoch(),
+    };
+    let template_table = ctx.db.poll_template();
+    if template_table.name().find(&name).is_some() {
+        template_table.name().update(template);
+    } else {
+        template_table.insert(template);
+    }
+    
+    Ok(())
+}
+
+#[reducer]
+pub fn instantiate_template(ctx: &ReducerContext, name: String) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    require_admin(ctx, "Only admins can instantiate templates")?;
+    
+    let name = name.trim().to_string();
+    validate_template_name(&name)?;
+    let template = match ctx.db.poll_template().name().find(&name) {
+        Some(template) => template,
+        None => return Err(fail(E_NOT_FOUND, "Template not found")),
+    };
+    
+    validate_template(&template)?;
+    for poll in template.polls {
+        insert_poll(ctx, poll.question, poll.options);
+    }
+    
+    Ok(())
+}
+
+// (option text, combined votes, polls offering it), matching options by text
+fn group_tally(per_poll: Vec<Vec<(PollOption, u64)>>) -> Vec<(String, u64, u32)> {
+    let mut tally: Vec<(String, u64, u32)> = Vec::new();
+    for (option, count) in per_poll.into_iter().flatten() {
+        let key = normalize_text(&option.text);
+        match tally.iter_mut().find(|(text, _, _)| normalize_text(text) == key) {
+            Some(entry) => {
+                entry.1 += count;
+                entry.2 += 1;
+            }
+            None => tally.push((option.text, count, 1)),
+        }
+    }
+    tally
+}
+
+// Combined tally across a group's polls, matching options by text
+#[reducer]
+pub fn group_results(ctx: &ReducerContext, group_id: u64) -> Result<(), String> {
+    let mut polls = ctx.db.poll().iter()
+        .filter(|p| p.group_id == Some(group_id))
+        .collect::<Vec<_>>();
+    if polls.is_empty() {
+        return Err(fail(E_NOT_FOUND, "Poll group not found"));
+    }
+    polls.sort_by_key(|p| (p.created_at, p.poll_id));
+    
+    let per_poll = polls.iter()
+        .map(|poll| option_counts(ctx, poll.poll_id))
+        .collect::<Vec<_>>();
+    let tally = group_tally(per_poll);
+    
+    let group_table = ctx.db.group_result();
+    group_table.group_id().delete(group_id);
+    for (option_text, vote_count, poll_count) in tally {
+        group_table.insert(GroupResult {
+            row_id: 0,
+            group_id,
+            option_text,
+            vote_count,
+            poll_count,
+        });
+    }
+    
+    Ok(())
+}
+
+// The leader is locked in once no other option can reach it even if every
+// remaining voter picked that option
+fn projection(poll_id: u64, mut counts: Vec<(PollOption, u64)>, remaining_voters: u64, now: i64) -> ProjectedWinnerView {
+    counts.sort_by(|(a, ca), (b, cb)| cb.cmp(ca).then(a.option_id.cmp(&b.option_id)));
+    
+    let leader_votes = counts.first().map_or(0, |(_, c)| *c);
+    let runner_up_votes = counts.get(1).map_or(0, |(_, c)| *c);
+    let leader_option_id = match counts.first() {
+        Some((option, c)) if *c > runner_up_votes => Some(option.option_id),
+        _ => None,
+    };
+    let contenders = counts.iter()
+        .filter(|(_, c)| c + remaining_voters >= leader_votes)
+        .map(|(o, _)| o.option_id)
+        .collect::<Vec<_>>();
+    
+    ProjectedWinnerView {
+        poll_id,
+        leader_option_id,
+        leader_votes,
+        runner_up_votes,
+        remaining_voters,
+        decided: leader_option_id.is_some() && contenders.len() == 1,
+        contenders,
+        computed_at: now,
+    }
+}
+
+// Reports whether the leader can still be caught by the voters who haven't
+// voted yet. Votes already cast are assumed to stay where they are.
+#[reducer]
+pub fn projected_winner(ctx: &ReducerContext, poll_id: u64) -> Result<(), String> {
+    if ctx.db.poll().poll_id().find(poll_id).is_none() {
+        return Err(fail(E_NOT_FOUND, "Poll not found"));
+    }
+    
+    let voted = ctx.db.vote().iter()
+        .filter(|v| v.poll_id == poll_id)
+        .map(|v| v.user_id)
+        .collect::<Vec<_>>();
+    let remaining_voters = ctx.db.user().iter()
+        .filter(|u| u.connected && !voted.contains(&u.user_id))
+        .count() as u64;
+    
+    let row = projection(poll_id, option_counts(ctx, poll_id), remaining_voters, ctx.timestamp.to_micros_since_unix_epoch());
+    
+    let projection_table = ctx.db.projected_winner_view();
+    if projection_table.poll_id().find(poll_id).is_some() {
+        projection_table.poll_id().update(row);
+    } else {
+        projection_table.insert(row);
+    }
+    
+    Ok(())
+}
+
+#[reducer]
+pub fn export_long(ctx: &ReducerContext, poll_id: u64) -> Result<(), String> {
+    require_admin(ctx, "Only admins can export results")?;
+    
+    let poll = match ctx.db.poll().poll_id().find(poll_id) {
+        Some(poll) => poll,
+        None => return Err(fail(E_NOT_FOUND, "Poll not found")),
+    };
+    
+    let export_table = ctx.db.export_long_row();
+    export_table.poll_id().delete(poll_id);
+    
+    let votes = ctx.db.vote().iter()
+        .filter(|v| v.poll_id == poll_id)
+        .collect::<Vec<_>>();
+    let options = ctx.db.poll_option().iter()
+        .filter(|o| o.poll_id == poll_id)
+        .collect::<Vec<_>>();
+    for row in long_rows(&poll, votes, &options) {
+        export_table.insert(row);
+    }
+    
+    Ok(())
+}
+
+// One row per counted vote, oldest first; anonymous polls null the user
+fn long_rows(poll: &Poll, mut votes: Vec<Vote>, options: &[PollOption]) -> Vec<ExportLongRow> {
+    votes.retain(|v| !v.voided && !v.provisional);
+    votes.sort_by_key(|v| (v.voted_at, v.vote_id));
+    votes.into_iter()
+        .map(|vote| ExportLongRow {
+            row_id: 0,
+            poll_id: poll.poll_id,
+            user_id: if poll.anonymous_reports { None } else { Some(vote.user_id) },
+            option_id: vote.option_id,
+            option_text: options.iter()
+                .find(|o| o.option_id == vote.option_id)
+                .map(|o| o.text.clone())
+                .unwrap_or_default(),
+            voted_at: vote.voted_at,
+        })
+        .collect()
+}
+
+// Polls grouped by trimmed, lowercased question, keeping only groups of two or more
+fn duplicate_groups(mut polls: Vec<Poll>) -> Vec<(String, Vec<u64>)> {
+    polls.sort_by_key(|p| p.poll_id);
+    let mut groups: Vec<(String, Vec<u64>)> = Vec::new();
+    for poll in polls {
+        let key = normalize_text(&poll.question);
+        match groups.iter_mut().find(|(k, _)| *k == key) {
+            Some(entry) => entry.1.push(poll.poll_id),
+            None => groups.push((key, vec![poll.poll_id])),
+        }
+    }
+    groups.retain(|(_, poll_ids)| poll_ids.len() > 1);
+    groups
+}
+
+#[reducer]
+pub fn find_duplicate_questions(ctx: &ReducerContext) -> Result<(), String> {
+    require_admin(ctx, "Only admins can check for duplicate questions")?;
+    
+    let polls = ctx.db.poll().iter().collect::<Vec<_>>();
+    
+    let duplicate_table = ctx.db.duplicate_question();
+    for row in duplicate_table.iter().collect::<Vec<_>>() {
+        duplicate_table.delete(row);
+    }
+    for (normalized_question, poll_ids) in duplicate_groups(polls) {
+        duplicate_table.insert(DuplicateQuestion { normalized_question, poll_ids });
+    }
+    
+    Ok(())
+}
+
+// Options nobody picked. option_counts treats a missing PollResult row as zero.
+fn unpicked_options(counts: Vec<(PollOption, u64)>) -> Vec<DeadOption> {
+    counts.into_iter()
+        .filter(|(_, count)| *count == 0)
+        .map(|(option, _)| DeadOption {
+            option_id: option.option_id,
+            poll_id: option.poll_id,
+            text: option.text,
+        })
+        .collect()
+}
+
+#[reducer]
+pub fn dead_options(ctx: &ReducerContext, poll_id: u64) -> Result<(), String> {
+    let poll = match ctx.db.poll().poll_id().find(poll_id) {
+        Some(poll) => poll,
+        None => return Err(fail(E_NOT_FOUND, "Poll not found")),
+    };
+    if poll.is_active {
+        return Err(fail(E_CONFLICT, "Poll is still open"));
+    }
+    
+    let dead_table = ctx.db.dead_option();
+    dead_table.poll_id().delete(poll_id);
+    for row in unpicked_options(option_counts(ctx, poll_id)) {
+        dead_table.insert(row);
+    }
+    
+    Ok(())
+}
+
+// Counted votes per option cast after `since_micros`. A cutoff in the future
+// simply matches no votes, so every option reports zero.
+fn deltas_since(options: &[PollOption], votes: &[Vote], since_micros: i64) -> Vec<ResultsDelta> {
+    options.iter()
+        .map(|option| ResultsDelta {
+            option_id: option.option_id,
+            poll_id: option.poll_id,
+            votes_gained: votes.iter()
+                .filter(|v| v.option_id == option.option_id && v.voted_at > since_micros && !v.voided && !v.provisional)
+                .count() as u64,
+            since_micros,
+        })
+        .collect()
+}
+
+#[reducer]
+pub fn results_since(ctx: &ReducerContext, poll_id: u64, since_micros: i64) -> Result<(), String> {
+    if ctx.db.poll().poll_id().find(poll_id).is_none() {
+        return Err(fail(E_NOT_FOUND, "Poll not found"));
+    }
+    
+    let delta_table = ctx.db.results_delta();
+    delta_table.poll_id().delete(poll_id);
+    
+    let votes = ctx.db.vote().iter()
+        .filter(|v| v.poll_id == poll_id)
+        .collect::<Vec<_>>();
+    let options = option_counts(ctx, poll_id).into_iter()
+        .map(|(option, _)| option)
+        .collect::<Vec<_>>();
+    for row in deltas_since(&options, &votes, since_micros) {
+        delta_table.insert(row);
+    }
+    
+    Ok(())
+}
+
+// Counted votes per (voter role, option), sorted; departed voters are "unknown"
+fn role_counts(votes: &[Vote], role_of: impl Fn(&str) -> Option<String>) -> Vec<(String, u64, u64)> {
+    let mut counts: Vec<(String, u64, u64)> = Vec::new();
+    for vote in votes.iter().filter(|v| !v.voided && !v.provisional) {
+        let role = role_of(&vote.user_id).unwrap_or_else(|| "unknown".to_string());
+        match counts.iter_mut().find(|(r, o, _)| *r == role && *o == vote.option_id) {
+            Some(entry) => entry.2 += 1,
+            None => counts.push((role, vote.option_id, 1)),
+        }
+    }
+    counts.sort_by(|a, b| a.0.cmp(&b.0).then(a.1.cmp(&b.1)));
+    counts
+}
+
+#[reducer]
+pub fn results_by_role(ctx: &ReducerContext, poll_id: u64) -> Result<(), String> {
+    require_admin(ctx, "Only admins can view results by role")?;
+    
+    let poll = match ctx.db.poll().poll_id().find(poll_id) {
+        Some(poll) => poll,
+        None => return Err(fail(E_NOT_FOUND, "Poll not found")),
+    };
+    // Splitting a small group by role could reveal individual votes
+    if poll.anonymous_reports {
+        return Err(fail(E_CONFLICT, "Role breakdowns are not available for anonymous polls"));
+    }
+    
+    let user_table = ctx.db.user();
+    let votes = ctx.db.vote().iter()
+        .filter(|v| v.poll_id == poll_id)
+        .collect::<Vec<_>>();
+    let counts = role_counts(&votes, |user_id| user_table.user_id().find(user_id.to_string()).map(|u| u.role));
+    
+    let role_table = ctx.db.role_result();
+    role_table.poll_id().delete(poll_id);
+    for (role, option_id, vote_count) in counts {
+        role_table.insert(RoleResult {
+            row_id: 0,
+            poll_id,
+            role,
+            option_id,
+            vote_count,
+        });
+    }
+    
+    Ok(())
+}
+
+#[reducer]
+pub fn validate_state(ctx: &ReducerContext) -> Result<(), String> {
+    let healed = heal_presentation_state(ctx);
+    if healed > 0 {
+        log::warn!("validate_state: reset {} state rows pointing at missing polls", healed);
+    }
+    Ok(())
+}
+
+// Reasons are listed without the user they came from, and abstentions
+// without a reason have nothing to list
+fn reason_row(abstention: Abstention) -> Option<AbstentionReason> {
+    abstention.reason.map(|reason| AbstentionReason {
+        abstention_id: abstention.abstention_id,
+        poll_id: abstention.poll_id,
+        reason,
+        abstained_at: abstention.abstained_at,
+    })
+}
+
+#[reducer]
+pub fn abstention_reasons(ctx: &ReducerContext, poll_id: u64) -> Result<(), String> {
+    require_admin(ctx, "Only admins can view abstention reasons")?;
+    
+    if ctx.db.poll().poll_id().find(poll_id).is_none() {
+        return Err(fail(E_NOT_FOUND, "Poll not found"));
+    }
+    
+    let reason_table = ctx.db.abstention_reason();
+    reason_table.poll_id().delete(poll_id);
+    for row in ctx.db.abstention().poll_id().filter(poll_id).filter_map(reason_row) {
+        reason_table.insert(row);
+    }
+    
+    Ok(())
+}
+
+// Pinned poll first, then by ID
+fn listed_poll_ids(mut polls: Vec<Poll>, user: &User) -> Vec<u64> {
+    polls.retain(|p| !p.archived && poll_visible_to(p, user));
+    polls.sort_by_key(|p| (!p.is_pinned, p.poll_id));
+    polls.iter().map(|p| p.poll_id).collect()
+}
+
+#[reducer]
+pub fn visible_polls(ctx: &ReducerContext) -> Result<(), String> {
+    let user_id = ctx.sender.to_string();
+    let user = match ctx.db.user().user_id().find(&user_id) {
+        Some(user) => user,
+        None => return Err(fail(E_NOT_FOUND, "User not found")),
+    };
+    
+    let poll_ids = listed_poll_ids(ctx.db.poll().iter().collect(), &user);
+    
+    let row = VisiblePollList {
+        user_id: user_id.clone(),
+        poll_ids,
+        computed_at: ctx.timestamp.to_micros_since_unix_epoch(),
+    };
+    
+    let list_table = ctx.db.visible_poll_list();
+    if list_table.user_id().find(&user_id).is_some() {
+        list_table.user_id().update(row);
+    } else {
+        list_table.insert(row);
+    }
+    
+    Ok(())
+}
+
+#[reducer]
+pub fn participation_streaks(ctx: &ReducerContext) -> Result<(), String> {
+    require_admin(ctx, "Only admins can compute participation streaks")?;
+    
+    let polls = ctx.db.poll().iter().collect::<Vec<_>>();
+    let votes = ctx.db.vote().iter().collect::<Vec<_>>();
+    let user_ids = ctx.db.user().iter().map(|u| u.user_id).collect::<Vec<_>>();
+    
+    let streak_table = ctx.db.participation_streak();
+    for row in streak_table.iter().collect::<Vec<_>>() {
+        streak_table.delete(row);
+    }
+    for row in longest_streaks(polls, &votes, user_ids) {
+        streak_table.insert(row);
+    }
+    
+    Ok(())
+}
+
+// Each user's longest run of consecutive polls in creation order with a
+// counted vote. One pass over the votes, so the cost is votes + users × polls.
+fn longest_streaks(mut polls: Vec<Poll>, votes: &[Vote], user_ids: Vec<String>) -> Vec<ParticipationStreak> {
+    polls.sort_by_key(|p| (p.created_at, p.poll_id));
+    let voted = votes.iter()
+        .filter(|v| !v.voided && !v.provisional)
+        .map(|v| (v.poll_id, v.user_id.as_str()))
+        .collect::<std::collections::HashSet<_>>();
+    
+    user_ids.into_iter()
+        .map(|user_id| {
+            let mut longest = 0;
+            let mut current = 0;
+            for poll in &polls {
+                if voted.contains(&(poll.poll_id, user_id.as_str())) {
+                    current += 1;
+                    longest = longest.max(current);
+                } else {
+                    current = 0;
+                }
+            }
+            ParticipationStreak {
+                user_id,
+                longest_streak: longest,
+            }
+        })
+        .collect()
+}
+
+// Polls sharing option texts with `poll_id`, ranked by Jaccard similarity
+fn similar_matches(poll_id: u64, options: Vec<PollOption>) -> Vec<(u64, f64)> {
+    // Normalized option texts per poll, deduplicated
+    let mut option_sets: Vec<(u64, Vec<String>)> = Vec::new();
+    for option in options {
+        let key = normalize_text(&option.text);
+        match option_sets.iter_mut().find(|(id, _)| *id == option.poll_id) {
+            Some((_, set)) if set.contains(&key) => {}
+            Some((_, set)) => set.push(key),
+            None => option_sets.push((option.poll_id, vec![key])),
+        }
+    }
+    let target = match option_sets.iter().find(|(id, _)| *id == poll_id) {
+        Some((_, set)) => set.clone(),
+        None => Vec::new(),
+    };
+    
+    let mut matches = option_sets.iter()
+        .filter(|(id, _)| *id != poll_id)
+        .filter_map(|(id, set)| {
+            let shared = set.iter().filter(|t| target.contains(t)).count();
+            let union = target.len() + set.len() - shared;
+            (shared > 0).then(|| (*id, shared as f64 / union as f64))
+        })
+        .collect::<Vec<_>>();
+    matches.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+    matches.truncate(MAX_SIMILAR_POLLS);
+    matches
+}
+
+#[reducer]
+pub fn similar_polls(ctx: &ReducerContext, poll_id: u64) -> Result<(), String> {
+    require_admin(ctx, "Only admins can look up similar polls")?;
+    
+    if ctx.db.poll().poll_id().find(poll_id).is_none() {
+        return Err(fail(E_NOT_FOUND, "Poll not found"));
+    }
+    
+    let options = ctx.db.poll_option().iter().collect::<Vec<_>>();
+    let matches = similar_matches(poll_id, options);
+    
+    let similar_table = ctx.db.similar_poll();
+    similar_table.poll_id().delete(poll_id);
+    for (similar_poll_id, similarity) in matches {
+        similar_table.insert(SimilarPoll {
+            row_id: 0,
+            poll_id,
+            similar_poll_id,
+            similarity,
+        });
+    }
+    
+    Ok(())
+}
+
+// (option, over option, ballots preferring it) for every ordered pair, and
+// the option that beats every other head to head, if any
+fn pairwise_preferences(option_ids: &[u64], ballots: &[RankedBallot]) -> (Vec<(u64, u64, u64)>, Option<u64>) {
+    // Position of an option on a ballot; unranked options tie for last
+    let rank = |ballot: &RankedBallot, option_id: u64| {
+        ballot.ranking.iter().position(|id| *id == option_id).unwrap_or(usize::MAX)
+    };
+    let preferred = |a: u64, b: u64| {
+        ballots.iter().filter(|ballot| rank(ballot, a) < rank(ballot, b)).count() as u64
+    };
+    
+    let mut pairs = Vec::new();
+    let mut winner = None;
+    for &a in option_ids {
+        let mut beats_all = true;
+        for &b in option_ids.iter().filter(|&&b| b != a) {
+            let a_over_b = preferred(a, b);
+            if a_over_b <= preferred(b, a) {
+                beats_all = false;
+            }
+            pairs.push((a, b, a_over_b));
+        }
+        if beats_all && !ballots.is_empty() {
+            winner = Some(a);
+        }
+    }
+    (pairs, winner)
+}
+
+#[reducer]
+pub fn condorcet_winner(ctx: &ReducerContext, poll_id: u64) -> Result<(), String> {
+    if ctx.db.poll().poll_id().find(poll_id).is_none() {
+        return Err(fail(E_NOT_FOUND, "Poll not found"));
+    }
+    
+    let mut option_ids = ctx.db.poll_option().iter()
+        .filter(|o| o.poll_id == poll_id)
+        .map(|o| o.option_id)
+        .collect::<Vec<_>>();
+    option_ids.sort();
+    let ballots = ctx.db.ranked_ballot().poll_id().filter(poll_id).collect::<Vec<_>>();
+    
+    let (pairs, winner) = pairwise_preferences(&option_ids, &ballots);
+    
+    let pair_table = ctx.db.pairwise_preference();
+    pair_table.poll_id().delete(poll_id);
+    for (option_id, over_option_id, ballot_count) in pairs {
+        pair_table.insert(PairwisePreference {
+            row_id: 0,
+            poll_id,
+            option_id,
+            over_option_id,
+            ballot_count,
+        });
+    }
+    
+    let row = CondorcetResult {
+        poll_id,
+        winner_option_id: winner,
+        cycle: winner.is_none() && !ballots.is_empty(),
+        ballot_count: ballots.len() as u64,
+        computed_at: ctx.timestamp.to_micros_since_unix_epoch(),
+    };
+    
+    let result_table = ctx.db.condorcet_result();
+    if result_table.poll_id().find(poll_id).is_some() {
+        result_table.poll_id().update(row);
+    } else {
+        result_table.insert(row);
+    }
+    
+    Ok(())
+}
+
+// Mean absolute difference over all pairs, divided by twice the mean.
+// With no votes or a single option there is nothing to be unequal about.
+fn gini_coefficient(counts: &[u64]) -> f64 {
+    let n = counts.len() as u64;
+    let total: u64 = counts.iter().sum();
+    if total == 0 || n < 2 {
+        return 0.0;
+    }
+    let abs_diff_sum: u64 = counts.iter()
+        .flat_map(|a| counts.iter().map(move |b| a.abs_diff(*b)))
+        .sum();
+    abs_diff_sum as f64 / (2 * n * total) as f64
+}
+
+#[reducer]
+pub fn vote_concentration(ctx: &ReducerContext, poll_id: u64) -> Result<(), String> {
+    if ctx.db.poll().poll_id().find(poll_id).is_none() {
+        return Err(fail(E_NOT_FOUND, "Poll not found"));
+    }
+    
+    let counts = option_counts(ctx, poll_id).into_iter()
+        .map(|(_, c)| c)
+        .collect::<Vec<_>>();
+    
+    let row = VoteConcentration {
+        poll_id,
+        gini: gini_coefficient(&counts),
+        option_count: counts.len() as u64,
+        total_votes: counts.iter().sum(),
+        computed_at: ctx.timestamp.to_micros_since_unix_epoch(),
+    };
+    
+    let concentration_table = ctx.db.vote_concentration_view();
+    if concentration_table.poll_id().find(poll_id).is_some() {
+        concentration_table.poll_id().update(row);
+    } else {
+        concentration_table.insert(row);
+    }
+    
+    Ok(())
+}
+
+fn server_time_row(user_id: String, now: Timestamp) -> ServerTime {
+    ServerTime {
+        user_id,
+        server_micros: now.to_micros_since_unix_epoch(),
+    }
+}
+
+// How many options are real contenders: 1 when everyone picks the same one,
+// the option count when votes are spread evenly. 0 without votes.
+fn inverse_simpson(counts: &[u64]) -> f64 {
+    let total: u64 = counts.iter().sum();
+    if total == 0 {
+        return 0.0;
+    }
+    let share_squares: f64 = counts.iter()
+        .map(|c| {
+            let share = *c as f64 / total as f64;
+            share * share
+        })
+        .sum();
+    1.0 / share_squares
+}
+
+#[reducer]
+pub fn effective_options(ctx: &ReducerContext, poll_id: u64) -> Result<(), String> {
+    if ctx.db.poll().poll_id().find(poll_id).is_none() {
+        return Err(fail(E_NOT_FOUND, "Poll not found"));
+    }
+    
+    let counts = option_counts(ctx, poll_id).into_iter()
+        .map(|(_, c)| c)
+        .collect::<Vec<_>>();
+    
+    let row = EffectiveOptions {
+        poll_id,
+        effective_options: inverse_simpson(&counts),
+        option_count: counts.len() as u64,
+        total_votes: counts.iter().sum(),
+        computed_at: ctx.timestamp.to_micros_since_unix_epoch(),
+    };
+    
+    let effective_table = ctx.db.effective_options_view();
+    if effective_table.poll_id().find(poll_id).is_some() {
+        effective_table.poll_id().update(row);
+    } else {
+        effective_table.insert(row);
+    }
+    
+    Ok(())
+}
+
+#[reducer]
+pub fn server_time(ctx: &ReducerContext) -> Result<(), String> {
+    let row = server_time_row(ctx.sender.to_string(), ctx.timestamp);
+    
+    let time_table = ctx.db.server_time_view();
+    if time_table.user_id().find(&row.user_id).is_some() {
+        time_table.user_id().update(row);
+    } else {
+        time_table.insert(row);
+    }
+    
+    Ok(())
+}
+
+// Cumulative turnout at the end of each bucket from `start` to `end`
+fn turnout_points(poll_id: u64, start: i64, end: i64, bucket_secs: u32, vote_times: &[i64], audience: u64) -> Result<Vec<TurnoutPoint>, String> {
+    let bucket_micros = bucket_secs as i64 * 1_000_000;
+    let bucket_count = (end - start) / bucket_micros + 1;
+    if bucket_count > MAX_TIMELINE_BUCKETS {
+        return Err(fail(E_INVALID_INPUT, format!(
+            "Curve would need {} buckets (max {}); use a larger bucket size",
+            bucket_count, MAX_TIMELINE_BUCKETS
+        )));
+    }
+    
+    let points = (0..bucket_count)
+        .map(|bucket| {
+            let bucket_end = (start + (bucket + 1) * bucket_micros).min(end);
+            let cumulative_votes = vote_times.iter().filter(|&&t| t <= bucket_end).count() as u64;
+            TurnoutPoint {
+                point_id: 0,
+                poll_id,
+                bucket: bucket as u32,
+                bucket_end,
+                cumulative_votes,
+                turnout_percent: percent(cumulative_votes, audience),
+            }
+        })
+        .collect();
+    Ok(points)
+}
+
+#[reducer]
+pub fn turnout_curve(ctx: &ReducerContext, poll_id: u64, bucket_secs: u32) -> Result<(), String> {
+    if bucket_secs == 0 {
+        return Err(fail(E_INVALID_INPUT, "Bucket size must be at least one second"));
+    }
+    
+    let poll = match ctx.db.poll().poll_id().find(poll_id) {
+        Some(poll) => poll,
+        None => return Err(fail(E_NOT_FOUND, "Poll not found")),
+    };
+    let start = match poll.activated_at {
+        Some(t) => t,
+        None => return Err(fail(E_CONFLICT, "Poll has not been activated")),
+    };
+    // Open polls are charted up to now
+    let end = if poll.is_active {
+        ctx.timestamp.to_micros_since_unix_epoch()
+    } else {
+        poll.closed_at.unwrap_or(start)
+    }.max(start);
+    
+    let vote_times = ctx.db.vote().iter()
+        .filter(|v| v.poll_id == poll_id && !v.voided && !v.provisional)
+        .map(|v| v.voted_at)
+        .collect::<Vec<_>>();
+    // Voters who have since disconnected still belong to the audience
+    let connected = ctx.db.user().iter().filter(|u| u.connected).count() as u64;
+    let audience = connected.max(vote_times.len() as u64);
+    let points = turnout_points(poll_id, start, end, bucket_secs, &vote_times, audience)?;
+    
+    let point_table = ctx.db.turnout_point();
+    point_table.poll_id().delete(poll_id);
+    for point in points {
+        point_table.insert(point);
+    }
+    
+    Ok(())
+}
+
+// (cohort, users, voters) for each cohort; `votes` are the poll's
+fn cohort_tallies(poll: &Poll, users: impl Iterator<Item = User>, votes: &[Vote]) -> Vec<(&'static str, u64, u64)> {
+    let voters = votes.iter()
+        .filter(|v| !v.voided && !v.provisional)
+        .map(|v| v.user_id.as_str())
+        .collect::<Vec<_>>();
+    
+    let cohorts: &[&str] = if poll.activated_at.is_some() {
+        &["before_open", "while_open", "after_close"]
+    } else {
+        &["not_opened"]
+    };
+    let mut tallies = cohorts.iter().map(|c| (*c, 0u64, 0u64)).collect::<Vec<_>>();
+    for user in users {
+        let cohort = match (poll.activated_at, poll.closed_at) {
+            (None, _) => "not_opened",
+            (Some(opened), _) if user.connected_at < opened => "before_open",
+            (Some(_), Some(closed)) if !poll.is_active && user.connected_at > closed => "after_close",
+            _ => "while_open",
+        };
+        if let Some(entry) = tallies.iter_mut().find(|(c, _, _)| *c == cohort) {
+            entry.1 += 1;
+            if voters.contains(&user.user_id.as_str()) {
+                entry.2 += 1;
+            }
+        }
+    }
+    tallies
+}
+
+// Users are grouped by when they (last) joined relative to the poll's open
+// period: "before_open", "while_open" or "after_close". A poll that was never
+// activated has no such period, so everyone lands in "not_opened".
+#[reducer]
+pub fn cohort_participation(ctx: &ReducerContext, poll_id: u64) -> Result<(), String> {
+    require_admin(ctx, "Only admins can view cohort participation")?;
+    
+    let poll = match ctx.db.poll().poll_id().find(poll_id) {
+        Some(poll) => poll,
+        None => return Err(fail(E_NOT_FOUND, "Poll not found")),
+    };
+    
+    let votes = ctx.db.vote().iter().filter(|v| v.poll_id == poll_id).collect::<Vec<_>>();
+    let tallies = cohort_tallies(&poll, ctx.db.user().iter(), &votes);
+    
+    let cohort_table = ctx.db.cohort_participation_row();
+    cohort_table.poll_id().delete(poll_id);
+    for (cohort, users, voted) in tallies {
+        cohort_table.insert(CohortParticipation {
+            row_id: 0,
+            poll_id,
+            cohort: cohort.to_string(),
+            users,
+            voters: voted,
+            turnout_percent: percent(voted, users),
+        });
+    }
+    
+    Ok(())
+}
+
+fn pacing(polls: impl Iterator<Item = Poll>, now: i64) -> PacingStats {
+    let mut activations = polls
+        .filter_map(|p| p.activated_at)
+        .collect::<Vec<_>>();
+    activations.sort();
+    
+    let gaps = activations.windows(2)
+        .map(|pair| pair[1] - pair[0])
+        .collect::<Vec<_>>();
+    let average_gap_micros = if gaps.is_empty() {
+        None
+    } else {
+        Some(gaps.iter().sum::<i64>() / gaps.len() as i64)
+    };
+    
+    PacingStats {
+        id: 0,
+        activation_count: activations.len() as u64,
+        average_gap_micros,
+        max_gap_micros: gaps.iter().copied().max(),
+        computed_at: now,
+    }
+}
+
+// Uses each poll's latest activated_at, so a reopened poll counts once at
+// its most recent activation
+#[reducer]
+pub fn pacing_stats(ctx: &ReducerContext) -> Result<(), String> {
+    require_admin(ctx, "Only admins can view pacing stats")?;
+    
+    let row = pacing(ctx.db.poll().iter(), ctx.timestamp.to_micros_since_unix_epoch());
+    
+    let stats_table = ctx.db.pacing_stats_view();
+    if stats_table.id().find(0).is_some() {
+        stats_table.id().update(row);
+    } else {
+        stats_table.insert(row);
+    }
+    
+    Ok(())
+}
+
+// Built from PollResult alone, so anonymous and named polls export the same way
+fn public_rows(poll: &Poll, counts: Vec<(PollOption, u64)>) -> Result<Vec<PublicExportRow>, String> {
+    // The export table is visible to everyone, so hidden polls stay out of it
+    if poll.is_draft || poll.visible_to_role.is_some() {
+        return Err(fail(E_CONFLICT, "Poll is not public"));
+    }
+    
+    let total: u64 = counts.iter().map(|(_, c)| c).sum();
+    let rows = counts.into_iter()
+        .map(|(option, vote_count)| PublicExportRow {
+            option_id: option.option_id,
+            poll_id: poll.poll_id,
+            question: poll.question.clone(),
+            option_text: option.text,
+            vote_count,
+            percent: percent(vote_count, total),
+        })
+        .collect();
+    Ok(rows)
+}
+
+#[reducer]
+pub fn public_export(ctx: &ReducerContext, poll_id: u64) -> Result<(), String> {
+    let poll = match ctx.db.poll().poll_id().find(poll_id) {
+        Some(poll) => poll,
+        None => return Err(fail(E_NOT_FOUND, "Poll not found")),
+    };
+    let rows = public_rows(&poll, option_counts(ctx, poll_id))?;
+    
+    let export_table = ctx.db.public_export_row();
+    export_table.poll_id().delete(poll_id);
+    for row in rows {
+        export_table.insert(row);
+    }
+    
+    Ok(())
+}
+
+// The value a counter must jump to so the next ID clears `highest`, if it lags
+fn synced_counter(current: u64, highest: u64) -> Option<u64> {
+    (highest > current).then_some(highest)
+}
+
+// Counters only move forward, so IDs of deleted rows are never handed out again
+#[reducer]
+pub fn sync_counters(ctx: &ReducerContext) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    require_admin(ctx, "Only admins can sync counters")?;
+    
+    for name in COUNTER_NAMES {
+        let current = ctx.db.counter().name().find(name.to_string()).map_or(0, |c| c.value);
+        let highest = max_id(ctx, name);
+        if let Some(value) = synced_counter(current, highest) {
+            log::warn!("sync_counters: {} counter was {} but IDs reach {}", name, current, highest);
+            set_counter(ctx, name, value);
+        }
+    }
+    
+    Ok(())
+}
+
+fn quorum_met(quorum: Option<u32>, voters: u64) -> bool {
+    quorum.is_none_or(|q| voters >= q as u64)
+}
+
+#[reducer]
+pub fn quorum_status(ctx: &ReducerContext, poll_id: u64) -> Result<(), String> {
+    let poll = match ctx.db.poll().poll_id().find(poll_id) {
+        Some(poll) => poll,
+        None => return Err(fail(E_NOT_FOUND, "Poll not found")),
+    };
+    
+    // Counted votes only; provisional and voided ones don't participate
+    let voters: u64 = ctx.db.poll_result().poll_id().filter(poll_id)
+        .map(|r| r.vote_count)
+        .sum();
+    let row = QuorumStatus {
+        poll_id,
+        quorum: poll.quorum,
+        voters,
+        met: quorum_met(poll.quorum, voters),
+        computed_at: ctx.timestamp.to_micros_since_unix_epoch(),
+    };
+    
+    let status_table = ctx.db.quorum_status_view();
+    if status_table.poll_id().find(poll_id).is_some() {
+        status_table.poll_id().update(row);
+    } else {
+        status_table.insert(row);
+    }
+    
+    Ok(())
+}
+
+// `users` is (total, connected)
+fn health_row(presentation: Option<PresentationState>, users: (u64, u64), total_votes: u64, sealed: bool, now: i64) -> Health {
+    let (state, current_poll_id) = match presentation {
+        Some(s) => (s.state, s.current_poll_id),
+        None => ("uninitialized".to_string(), 0),
+    };
+    Health {
+        id: 0,
+        state,
+        current_poll_id,
+        total_users: users.0,
+        connected_users: users.1,
+        total_votes,
+        schema_version: SCHEMA_VERSION,
+        sealed,
+        checked_at: now,
+    }
+}
+
+#[reducer]
+pub fn health(ctx: &ReducerContext) -> Result<(), String> {
+    // Table counts are tracked by the database, so only the connected filter scans
+    let user_table = ctx.db.user();
+    let connected = user_table.iter().filter(|u| u.connected).count() as u64;
+    let row = health_row(
+        ctx.db.presentation_state().id().find(0),
+        (user_table.count(), connected),
+        ctx.db.vote().count(),
+        get_settings(ctx).sealed,
+        ctx.timestamp.to_micros_since_unix_epoch(),
+    );
+    
+    let health_table = ctx.db.health_view();
+    if health_table.id().find(0).is_some() {
+        health_table.id().update(row);
+    } else {
+        health_table.insert(row);
+    }
+    
+    Ok(())
+}
+
+// Seeded from the draw time and every entry, so the outcome can be checked
+// afterwards but can't be predicted before the draw. `votes` are the poll's.
+fn raffle_draw(poll_id: u64, votes: Vec<Vote>, drawn_at: i64) -> Result<Raffle, String> {
+    let mut entrants = votes.into_iter()
+        .filter(|v| !v.voided && !v.provisional && !v.is_demo)
+        .collect::<Vec<_>>();
+    if entrants.is_empty() {
+        return Err(fail(E_CONFLICT, "No eligible voters"));
+    }
+    entrants.sort_by_key(|v| v.vote_id);
+    
+    let seed = entrants.iter()
+        .fold(drawn_at as u64, |hash, v| chain_checksum(hash, v.vote_id, v.option_id));
+    let winner = &entrants[(seed % entrants.len() as u64) as usize];
+    Ok(Raffle {
+        poll_id,
+        winner_user_id: winner.user_id.clone(),
+        entrant_count: entrants.len() as u64,
+        seed,
+        drawn_at,
+    })
+}
+
+#[reducer]
+pub fn draw_winner(ctx: &ReducerContext, poll_id: u64) -> Result<(), String> {
+    guard_mutation(ctx)?;
+    require_admin(ctx, "Only admins can draw a winner")?;
+    
+    let poll = match ctx.db.poll().poll_id().find(poll_id) {
+        Some(poll) => poll,
+        None => return Err(fail(E_NOT_FOUND, "Poll not found")),
+    };
+    if poll.anonymous_reports {
+        return Err(fail(E_CONFLICT, "Cannot draw a winner from an anonymous poll"));
+    }
+    
+    let votes = ctx.db.vote().iter().filter(|v| v.poll_id == poll_id).collect::<Vec<_>>();
+    let row = raffle_draw(poll_id, votes, ctx.timestamp.to_micros_since_unix_epoch())?;
+    let raffle_table = ctx.db.raffle();
+    if raffle_table.poll_id().find(poll_id).is_some() {
+        raffle_table.poll_id().update(row);
+    } else {
+        raffle_table.insert(row);
+    }
+    
+    Ok(())
+}
+
+// (option a, option b, ballots picking both) with a < b, sorted
+fn co_selection_pairs(ballots: impl Iterator<Item = ChoiceBallot>) -> Vec<(u64, u64, u64)> {
+    let mut pairs: Vec<(u64, u64, u64)> = Vec::new();
+    for ballot in ballots {
+        // Stored sorted and deduplicated, so a < b below
+        let options = ballot.option_ids;
+        for (i, &a) in options.iter().enumerate() {
+            for &b in &options[i + 1..] {
+                match pairs.iter_mut().find(|(x, y, _)| *x == a && *y == b) {
+                    Some(entry) => entry.2 += 1,
+                    None => pairs.push((a, b, 1)),
+                }
+            }
+        }
+    }
+    pairs.sort();
+    pairs
+}
+
+// Counts, for each pair of options, how many multi-choice ballots (see
+// submit_choices) picked both
+#[reducer]
+pub fn co_selection(ctx: &ReducerContext, poll_id: u64) -> Result<(), String> {
+    if ctx.db.poll().poll_id().find(poll_id).is_none() {
+        return Err(fail(E_NOT_FOUND, "Poll not found"));
+    }
+    
+    let pairs = co_selection_pairs(ctx.db.choice_ballot().poll_id().filter(poll_id));
+    
+    let pair_table = ctx.db.co_selection_pair();
+    pair_table.poll_id().delete(poll_id);
+    for (option_a, option_b, user_count) in pairs {
+        pair_table.insert(CoSelectionPair {
+            row_id: 0,
+            poll_id,
+            option_a,
+            option_b,
+            user_count,
+        });
+    }
+    
+    Ok(())
+}
+
+// Anything without a translation falls back to the original text
+fn localized_options(options: Vec<PollOption>, translated_text: impl Fn(u64) -> Option<String>) -> Vec<LocalizedOption> {
+    options.into_iter()
+        .map(|option| match translated_text(option.option_id) {
+            Some(text) => LocalizedOption { option_id: option.option_id, text, translated: true },
+            None => LocalizedOption { option_id: option.option_id, text: option.text, translated: false },
+        })
+        .collect()
+}
+
+#[reducer]
+pub fn localized_poll(ctx: &ReducerContext, poll_id: u64, lang: String) -> Result<(), String> {
+    let user_id = ctx.sender.to_string();
+    let user = match ctx.db.user().user_id().find(&user_id) {
+        Some(user) => user,
+        None => return Err(fail(E_NOT_FOUND, "User not found")),
+    };
+    let lang = normalize_lang(&lang)?;
+    let poll = match ctx.db.poll().poll_id().find(poll_id) {
+        Some(poll) if poll_visible_to(&poll, &user) => poll,
+        _ => return Err(fail(E_NOT_FOUND, "Poll not found")),
+    };
+    
+    let translation = ctx.db.poll_translation().poll_id().filter(poll_id).find(|t| t.lang == lang);
+    let option_translation_table = ctx.db.poll_option_translation();
+    let options = option_counts(ctx, poll_id).into_iter().map(|(option, _)| option).collect();
+    let options = localized_options(options, |option_id| {
+        option_translation_table.option_id().filter(option_id).find(|t| t.lang == lang).map(|t| t.text)
+    });
+    
+    let row = LocalizedPoll {
+        user_id: user_id.clone(),
+        poll_id,
+        lang,
+        question_translated: translation.is_some(),
+        question: translation.map_or(poll.question, |t| t.question),
+        options,
+    };
+    
+    let view_table = ctx.db.localized_poll_view();
+    if view_table.user_id().find(&user_id).is_some() {
+        view_table.user_id().update(row);
+    } else {
+        view_table.insert(row);
+    }
+    
+    Ok(())
+}
+
+// The report text and how many polls it covers
+fn report_text(polls: Vec<Poll>, counts_of: impl Fn(u64) -> Vec<(PollOption, u64)>) -> (String, u32) {
+    // Polls that ran and were closed, in the order they closed
+    let mut polls = polls.into_iter()
+        .filter(|p| !p.is_active && p.closed_at.is_some())
+        .collect::<Vec<_>>();
+    polls.sort_by_key(|p| (p.closed_at, p.poll_id));
+    
+    let mut lines = vec!["Results report".to_string(), String::new()];
+    if polls.is_empty() {
+        lines.push("No polls have been finalized yet.".to_string());
+    }
+    for (index, poll) in polls.iter().enumerate() {
+        let counts = counts_of(poll.poll_id);
+        let total: u64 = counts.iter().map(|(_, c)| c).sum();
+        let top = counts.iter().map(|(_, c)| *c).max().unwrap_or(0);
+        
+        lines.push(format!("{}. {}", index + 1, poll.question));
+        let leaders = counts.iter()
+            .filter(|(_, c)| total > 0 && *c == top)
+            .map(|(o, _)| o.text.as_str())
+            .collect::<Vec<_>>();
+        match leaders.as_slice() {
+            [] => lines.push("   No votes recorded".to_string()),
+            [winner] => lines.push(format!("   Winner: {} ({:.1}%)", winner, percent(top, total))),
+            _ => lines.push(format!("   Tie: {} ({:.1}% each)", leaders.join(", "), percent(top, total))),
+        }
+        for (option, count) in counts.iter() {
+            lines.push(format!("   - {}: {} ({:.1}%)", option.text, plural(*count, "vote"), percent(*count, total)));
+        }
+        lines.push(String::new());
+    }
+    
+    (lines.join("\n").trim_end().to_string(), polls.len() as u32)
+}
+
+#[reducer]
+pub fn report(ctx: &ReducerContext) -> Result<(), String> {
+    require_admin(ctx, "Only admins can generate reports")?;
+    
+    let (text, poll_count) = report_text(ctx.db.poll().iter().collect(), |poll_id| option_counts(ctx, poll_id));
+    let row = Report {
+        id: 0,
+        text,
+        poll_count,
+        generated_at: ctx.timestamp.to_micros_since_unix_epoch(),
+    };
+    
+    let report_table = ctx.db.results_report();
+    if report_table.id().find(0).is_some() {
+        report_table.id().update(row);
+    } else {
+        report_table.insert(row);
+    }
+    
+    Ok(())
+}
+
+// ======== Initial Setup ========
+
+// The earliest-joined user still connected, who takes over when the last
+// admin leaves
+fn admin_successor(users: &[User]) -> Option<&User> {
+    users.iter()
+        .filter(|u| u.connected)
+        .min_by_key(|u| u.connected_at)
+}
+
+#[reducer(client_disconnected)]
+pub fn client_disconnected(ctx: &ReducerContext) {
+    let user_table = ctx.db.user();
+    let user = match user_table.user_id().find(ctx.sender.to_string()) {
+        Some(user) => user,
+        None => return,
+    };
+    let was_admin = user.role == "admin";
+    user_table.user_id().update(User {
+        connected: false,
+        last_seen: ctx.timestamp.to_micros_since_unix_epoch(),
+        ..user
+    });
+    
+    // Don't leave participants stuck when the last admin leaves
+    let users: Vec<User> = user_table.iter().collect();
+    if was_admin && !users.iter().any(|u| u.connected && u.role == "admin") {
+        match admin_successor(&users).cloned() {
+            Some(successor) if get_settings(ctx).auto_promote_admin => {
+                log::info!("Last admin left; promoting {}", successor.user_id);
+                user_table.user_id().update(User {
+                    role: "admin".to_string(),
+                    ..successor
+                });
+            }
+            _ => {
+                // Pause until an admin moves the presentation on again
+                let presentation_table = ctx.db.presentation_state();
+                if let Some(state) = presentation_table.id().find(0) {
+                    if state.state != "ended" {
+                        presentation_table.id().update(PresentationState {
+                            state: "paused".to_string(),
+                            reveal_at: None,
+                            ..state
+                        });
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[reducer(init)]
+pub fn init(ctx: &ReducerContext) {
+    // Initialize the presentation state
+    let initial_state = PresentationState {
+        id: 0,
+        current_poll_id: 0,
+        state: "waiting".to_string(),
+        reveal_at: None,
+    };
+    ctx.db.presentation_state().insert(initial_state);
+    ctx.db.settings().insert(Settings {
+        owner_id: ctx.sender.to_string(),
+        ..default_settings()
+    });
+    ensure_cleanup_scheduled(ctx);
+    ctx.db.meta().insert(meta_row(SCHEMA_VERSION, ctx.timestamp.to_micros_since_unix_epoch()));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    const SEC: i64 = 1_000_000;
+    
+    fn poll(poll_id: u64) -> Poll {
+        Poll {
+            poll_id,
+            question: format!("Question {}", poll_id),
+            is_active: false,
+            created_at: poll_id as i64 * SEC,
+            activated_at: None,
+            anonymous_reports: false,
+            archived: false,
+            allow_change: true,
+            public_ballots: false,
+            closed_at: None,
+            close_reason: None,
+            allow_write_ins: false,
+            scheduled_open_at: None,
+            correct_option_id: None,
+            max_votes: None,
+            created_by: None,
+            creator_can_vote: true,
+            group_id: None,
+            requires_poll_id: None,
+            max_changes: None,
+            visible_to_role: None,
+            is_draft: false,
+            window_start: None,
+            window_end: None,
+            is_pinned: false,
+            quorum: None,
+        }
+    }
+    
+    fn vote(vote_id: u64, poll_id: u64, user_id: &str, option_id: u64, voted_at: i64) -> Vote {
+        Vote {
+            vote_id,
+            poll_id,
+            user_id: user_id.to_string(),
+            option_id,
+            voted_at,
+            source: None,
+            change_count: 0,
+            voided: false,
+            provisional: false,
+            weight: 1,
+            cast_by: None,
+            is_demo: false,
+        }
+    }
+    
+    // synth-102
+    
+    #[test]
+    fn timeline_buckets_votes_from_activation() {
+        let p = Poll { activated_at: Some(100 * SEC), ..poll(1) };
+        let votes = vec![
+            vote(1, 1, "a", 10, 101 * SEC),
+            vote(2, 1, "b", 10, 109 * SEC),
+            vote(3, 1, "c", 11, 112 * SEC),
+            vote(4, 1, "d", 10, 125 * SEC),
+        ];
+        let rows = timeline_rows(&p, &votes, 10).unwrap();
+        let cells = rows.iter().map(|r| (r.bucket, r.option_id, r.vote_count)).collect::<Vec<_>>();
+        assert_eq!(cells, vec![(0, 10, 2), (1, 11, 1), (2, 10, 1)]);
+        assert_eq!(rows[1].bucket_start, 110 * SEC);
+    }
+    
+    #[test]
+    fn timeline_starts_at_earliest_vote_without_activation() {
+        let votes = vec![vote(1, 1, "a", 10, 50 * SEC), vote(2, 1, "b", 10, 61 * SEC)];
+        let rows = timeline_rows(&poll(1), &votes, 10).unwrap();
+        assert_eq!(rows[0].bucket_start, 50 * SEC);
+        assert_eq!(rows.iter().map(|r| r.bucket).collect::<Vec<_>>(), vec![0, 1]);
+    }
+    
+    #[test]
+    fn timeline_rejects_too_many_buckets() {
+        let votes = vec![vote(1, 1, "a", 10, 0), vote(2, 1, "b", 10, 1_000 * SEC)];
+        let err = timeline_rows(&poll(1), &votes, 1).unwrap_err();
+        assert!(err.starts_with(E_INVALID_INPUT));
+    }
+    
+    // synth-103
+    
+    #[test]
+    fn simultaneous_open_polls_accept_votes() {
+        let survey = [Poll { is_active: true, ..poll(1) }, Poll { is_active: true, ..poll(2) }];
+        for p in survey.iter() {
+            assert!(voting_open_at(p, 10 * SEC, 0).is_ok());
+        }
+        assert!(voting_open_at(&poll(3), 10 * SEC, 0).is_err());
+    }
+    
+    #[test]
+    fn presenting_closes_other_polls_only_in_guided_mode() {
+        let open = Poll { is_active: true, ..poll(1) };
+        assert!(!closes_on_present(&open, 2, false));
+        assert!(closes_on_present(&open, 2, true));
+        assert!(!closes_on_present(&open, 1, true));
+    }
+    
+    // synth-104
+    
+    #[test]
+    fn audit_reports_duplicate_vote() {
+        let votes = vec![vote(1, 1, "a", 10, 0), vote(2, 1, "b", 10, 0), vote(3, 1, "a", 11, 0)];
+        let findings = vote_anomalies(&votes, &[10, 11], |_| true);
+        assert_eq!(findings.len(), 1);
+        assert_eq!((findings[0].0, findings[0].1), (3, "duplicate_vote"));
+    }
+    
+    #[test]
+    fn audit_reports_missing_options_and_departed_users() {
+        let votes = vec![vote(1, 1, "a", 99, 0), vote(2, 1, "gone", 10, 0)];
+        let findings = vote_anomalies(&votes, &[10], |u| u != "gone");
+        let kinds = findings.iter().map(|f| (f.0, f.1)).collect::<Vec<_>>();
+        assert_eq!(kinds, vec![(1, "missing_option"), (2, "unknown_user")]);
+    }
+    
+    // synth-105
+    
+    fn option(option_id: u64, poll_id: u64) -> PollOption {
+        PollOption {
+            option_id,
+            poll_id,
+            text: format!("Option {}", option_id),
+            image_url: None,
+            metadata: None,
+            added_by: None,
+        }
+    }
+    
+    #[test]
+    fn repair_renumbers_messy_ids_consistently() {
+        // Polls 7 and 3 (created in that order), colliding option IDs, a gap,
+        // and an option plus a vote for a poll that no longer exists
+        let polls = vec![Poll { created_at: 1, ..poll(7) }, Poll { created_at: 2, ..poll(3) }];
+        let options = vec![option(5, 3), option(5, 7), option(9, 7), option(4, 42)];
+        let mut sorted_options = options.clone();
+        sorted_options.sort_by_key(|o| (o.poll_id, o.option_id));
+        let (poll_map, option_map) = renumber_ids(&polls, &sorted_options);
+        assert_eq!(poll_map, vec![(7, 1), (3, 2)]);
+        assert_eq!(option_map, vec![((3, 5), 1), ((7, 5), 2), ((7, 9), 3)]);
+        
+        let votes = vec![vote(40, 7, "a", 9, 1), vote(12, 3, "b", 5, 2), vote(12, 42, "c", 4, 3)];
+        let votes = renumber_votes(votes, &poll_map, &option_map);
+        let cells = votes.iter().map(|v| (v.vote_id, v.poll_id, v.option_id)).collect::<Vec<_>>();
+        assert_eq!(cells, vec![(1, 1, 3), (2, 2, 1)]);
+    }
+    
+    #[test]
+    fn upgraded_databases_can_claim_an_owner_once() {
+        // Without a Settings row get_settings falls back to the defaults
+        let claimed = claimed_settings(default_settings(), "host".to_string()).unwrap();
+        assert_eq!(claimed.owner_id, "host");
+        assert!(claimed_settings(claimed, "other".to_string()).unwrap_err().starts_with(E_CONFLICT));
+    }
+    
+    // synth-106
+    
+    #[test]
+    fn unique_voters_counts_overlapping_voters_once() {
+        let votes = vec![
+            vote(1, 1, "alice", 10, 0),
+            vote(2, 2, "alice", 20, 0),
+            vote(3, 2, "bob", 20, 0),
+            vote(4, 3, "carol", 30, 0),
+            Vote { is_demo: true, ..vote(5, 1, "demo", 10, 0) },
+        ];
+        let user_ids = distinct_voters(&votes, |poll_id| poll_id == 3);
+        assert_eq!(user_ids, vec!["alice".to_string(), "bob".to_string()]);
+    }
+    
+    // synth-107
+    
+    #[test]
+    fn next_poll_walks_polls_in_creation_order() {
+        let polls = vec![poll(3), poll(1), Poll { archived: true, ..poll(2) }, poll(4)];
+        let mut shown = Vec::new();
+        let mut current = None;
+        while let Some(next) = next_in_order(&polls, current) {
+            shown.push(next.poll_id);
+            current = Some(next);
+        }
+        assert_eq!(shown, vec![1, 3, 4]);
+    }
+    
+    // synth-108
+    
+    #[test]
+    fn previous_poll_stops_at_first_poll_and_skips_drafts() {
+        let polls = vec![poll(1), Poll { is_draft: true, ..poll(2) }, poll(3)];
+        assert_eq!(previous_in_order(&polls, &polls[2]).map(|p| p.poll_id), Some(1));
+        assert!(previous_in_order(&polls, &polls[0]).is_none());
+    }
+    
+    // synth-109
+    
+    #[test]
+    fn locked_poll_rejects_a_second_vote() {
+        let locked = Poll { allow_change: false, ..poll(1) };
+        let err = check_vote_change(&locked, &vote(1, 1, "a", 10, 0), 11, false).unwrap_err();
+        assert_eq!(err, fail(E_CONFLICT, "Vote already cast and changes are disabled"));
+    }
+    
+    #[test]
+    fn votes_can_change_by_default() {
+        assert_eq!(check_vote_change(&poll(1), &vote(1, 1, "a", 10, 0), 11, false), Ok(true));
+        assert_eq!(check_vote_change(&poll(1), &vote(1, 1, "a", 10, 0), 10, false), Ok(false));
+    }
+    
+    // synth-110
+    
+    fn user(user_id: &str, role: &str) -> User {
+        User {
+            user_id: user_id.to_string(),
+            session_id: "default".to_string(),
+            role: role.to_string(),
+            connected_at: 0,
+            connected: true,
+            seat: None,
+            last_created_poll_at: None,
+            last_seen: 0,
+            platform: None,
+            vote_weight: 1,
+        }
+    }
+    
+    #[test]
+    fn unvoted_polls_lists_open_polls_without_a_vote() {
+        let polls = vec![
+            Poll { is_active: true, ..poll(1) },
+            Poll { is_active: true, ..poll(2) },
+            Poll { is_active: true, archived: true, ..poll(3) },
+            poll(4),
+        ];
+        let votes = vec![vote(1, 1, "a", 10, 0)];
+        assert_eq!(unvoted_poll_ids(&polls, &votes, &user("a", "user")), vec![2]);
+    }
+    
+    // synth-111
+    
+    #[test]
+    fn option_from_another_poll_is_reported_as_such() {
+        let err = option_for_poll(Some(option(5, 2)), 1).unwrap_err();
+        assert_eq!(err, fail(E_INVALID_INPUT, "Option belongs to a different poll"));
+    }
+    
+    #[test]
+    fn missing_option_is_reported_as_not_found() {
+        let err = option_for_poll(None, 1).unwrap_err();
+        assert_eq!(err, fail(E_NOT_FOUND, "Option not found for this poll"));
+        assert_eq!(option_for_poll(Some(option(5, 1)), 1).map(|o| o.option_id), Ok(5));
+    }
+    
+    // synth-112
+    
+    #[test]
+    fn full_session_turns_away_users_but_not_admins() {
+        let settings = Settings { max_participants: Some(2), ..default_settings() };
+        let mut users = vec![user("a", "user")];
+        assert!(check_admission(&settings, "user", &users).is_ok());
+        users.push(user("b", "user"));
+        users.push(user("host", "admin"));
+        assert_eq!(check_admission(&settings, "user", &users), Err(fail(E_CLOSED, "Session is full")));
+        assert!(check_admission(&settings, "admin", &users).is_ok());
+    }
+    
+    // synth-113
+    
+    #[test]
+    fn summary_names_the_winner() {
+        let counts = vec![(option(1, 1), 57), (option(2, 1), 43)];
+        assert_eq!(summary_outcome(&counts), "Winner: Option 1 with 57% (57 votes)");
+    }
+    
+    #[test]
+    fn summary_reports_ties_and_empty_polls() {
+        let counts = vec![(option(1, 1), 2), (option(2, 1), 2), (option(3, 1), 0)];
+        assert_eq!(summary_outcome(&counts), "Tie between Option 1 and Option 2 with 50% (2 votes each)");
+        let counts = vec![(option(1, 1), 0), (option(2, 1), 0)];
+        assert_eq!(summary_outcome(&counts), "No votes recorded");
+    }
+    
+    // synth-114
+    
+    #[test]
+    fn ballots_need_admin_unless_public() {
+        let viewer = user("a", "user");
+        assert!(check_ballot_access(&poll(1), &viewer).is_err());
+        assert!(check_ballot_access(&poll(1), &user("host", "admin")).is_ok());
+        assert!(check_ballot_access(&Poll { public_ballots: true, ..poll(1) }, &viewer).is_ok());
+    }
+    
+    #[test]
+    fn anonymous_ballots_are_never_exposed() {
+        let secret = Poll { anonymous_reports: true, public_ballots: true, ..poll(1) };
+        assert!(check_ballot_access(&secret, &user("a", "user")).is_err());
+        assert!(check_ballot_access(&secret, &user("host", "admin")).is_err());
+    }
+    
+    // synth-115
+    
+    #[test]
+    fn rejoin_keeps_the_existing_role() {
+        let demoted = User { connected: false, ..user("a", "user") };
+        let rejoined = rejoined_user(demoted, "other".to_string(), None, 5 * SEC);
+        assert_eq!(rejoined.role, "user");
+        assert!(rejoined.connected);
+        assert_eq!((rejoined.session_id.as_str(), rejoined.last_seen), ("other", 5 * SEC));
+    }
+    
+    // synth-116
+    
+    #[test]
+    fn a_vote_only_touches_the_affected_options() {
+        assert_eq!(result_deltas(None, 10, false, 1), vec![(10, 1)]);
+        assert_eq!(result_deltas(None, 10, true, 1), vec![]);
+        let existing = vote(1, 1, "a", 10, 0);
+        assert_eq!(result_deltas(Some(&existing), 11, false, 1), vec![(10, -1), (11, 1)]);
+        assert_eq!(result_deltas(Some(&existing), 10, false, 1), vec![]);
+        let pending = Vote { provisional: true, ..existing };
+        assert_eq!(result_deltas(Some(&pending), 10, false, 1), vec![(10, 1)]);
+    }
+    
+    // synth-117
+    
+    #[test]
+    fn seat_distribution_counts_users_per_seat() {
+        let users = vec![
+            User { seat: Some(2), ..user("a", "user") },
+            User { seat: Some(1), ..user("b", "user") },
+            User { seat: Some(2), ..user("c", "user") },
+            user("d", "user"),
+        ];
+        assert_eq!(seat_counts(&users), vec![(2, 2), (1, 1)]);
+    }
+    
+    // synth-118
+    
+    fn text_option(option_id: u64, poll_id: u64, text: &str) -> PollOption {
+        PollOption { text: text.to_string(), ..option(option_id, poll_id) }
+    }
+    
+    #[test]
+    fn comparison_matches_options_by_text() {
+        let counts_a = vec![(text_option(1, 1, "Yes"), 3), (text_option(2, 1, "No"), 1)];
+        let counts_b = vec![(text_option(3, 2, " yes"), 1), (text_option(4, 2, "Maybe"), 1)];
+        let rows = comparison_rows(&counts_a, &counts_b);
+        assert_eq!(rows, vec![
+            ("Yes".to_string(), Some(75.0), Some(50.0)),
+            ("No".to_string(), Some(25.0), None),
+            ("Maybe".to_string(), None, Some(50.0)),
+        ]);
+    }
+    
+    // synth-119
+    
+    #[test]
+    fn close_reason_is_stored_with_the_close_time() {
+        let reason = validate_close_reason(Some("  time expired ".to_string())).unwrap();
+        let closed = closed_poll(Poll { is_active: true, ..poll(1) }, 9 * SEC, reason);
+        assert!(!closed.is_active);
+        assert_eq!(closed.closed_at, Some(9 * SEC));
+        assert_eq!(closed.close_reason.as_deref(), Some("time expired"));
+        assert!(validate_close_reason(Some("x".repeat(MAX_CLOSE_REASON_LEN + 1))).is_err());
+    }
+    
+    // synth-120
+    
+    #[test]
+    fn did_not_vote_counts_connected_non_voters() {
+        let counts = vec![(option(1, 1), 2), (option(2, 1), 1)];
+        assert_eq!(non_voter_count(&counts, 5), 2);
+        assert_eq!(non_voter_count(&counts, 1), 0);
+    }
+    
+    // synth-121
+    
+    fn option_input(text: &str, image_url: Option<&str>) -> OptionInput {
+        OptionInput {
+            text: text.to_string(),
+            image_url: image_url.map(str::to_string),
+            metadata: None,
+        }
+    }
+    
+    #[test]
+    fn rich_options_accept_http_image_urls() {
+        let options = vec![
+            option_input("Cat", Some("https://example.com/cat.png")),
+            option_input("Dog", Some("http://example.com/dog.png")),
+            option_input("Neither", None),
+        ];
+        assert!(validate_options(&options).is_ok());
+    }
+    
+    #[test]
+    fn rich_options_reject_bad_image_urls() {
+        assert!(validate_options(&[option_input("Cat", Some("ftp://example.com/cat.png"))]).is_err());
+        let long_url = format!("https://{}", "a".repeat(MAX_IMAGE_URL_LEN));
+        assert!(validate_options(&[option_input("Cat", Some(&long_url))]).is_err());
+    }
+    
+    // synth-122
+    
+    #[test]
+    fn earliest_connected_participant_succeeds_the_last_admin() {
+        let users = vec![
+            User { connected: false, ..user("host", "admin") },
+            User { connected_at: 3 * SEC, ..user("late", "user") },
+            User { connected_at: SEC, ..user("early", "user") },
+            User { connected: false, connected_at: 0, ..user("gone", "user") },
+        ];
+        assert_eq!(admin_successor(&users).map(|u| u.user_id.as_str()), Some("early"));
+        assert!(admin_successor(&users[..1]).is_none());
+    }
+    
+    // synth-123
+    
+    fn link(link_id: u64, vote_id: u64, option_id: u64) -> VoteChecksumLink {
+        VoteChecksumLink { link_id, poll_id: 1, vote_id, option_id }
+    }
+    
+    #[test]
+    fn checksum_chain_verifies_and_detects_tampering() {
+        // Three votes, then b switches to 10 and c's vote is deleted
+        let links = vec![link(1, 1, 10), link(2, 2, 11), link(3, 3, 10), link(4, 2, 10), link(5, 3, VOTE_REMOVED)];
+        let running = links.iter().fold(0, |acc, l| chain_checksum(acc, l.vote_id, l.option_id));
+        let votes = vec![vote(1, 1, "a", 10, 0), vote(2, 1, "b", 10, 0)];
+        let report = integrity_report(1, running, links.clone(), &votes, SEC);
+        assert!(report.valid && report.rows_match);
+        assert_eq!(report.computed_checksum, running);
+        
+        // An edited row fails even though the chain itself is intact
+        let mut tampered = votes.clone();
+        tampered[1].option_id = 11;
+        let report = integrity_report(1, running, links.clone(), &tampered, SEC);
+        assert!(!report.valid && !report.rows_match);
+        
+        // A later legitimate change only extends the chain, so it still fails
+        let mut extended = links.clone();
+        extended.push(link(6, 1, 11));
+        let running = chain_checksum(running, 1, 11);
+        tampered[0].option_id = 11;
+        assert!(!integrity_report(1, running, extended.clone(), &tampered, SEC).valid);
+        
+        // Rewriting the links to cover the edit breaks the stored checksum
+        let forged = extended.into_iter()
+            .map(|l| if l.link_id == 4 { link(4, 2, 11) } else { l })
+            .collect::<Vec<_>>();
+        assert!(!integrity_report(1, running, forged, &tampered, SEC).valid);
+    }
+    
+    // synth-124
+    
+    #[test]
+    fn question_length_limit_is_inclusive() {
+        assert!(validate_question(&"q".repeat(MAX_QUESTION_LEN)).is_ok());
+        let err = validate_question(&"q".repeat(MAX_QUESTION_LEN + 1)).unwrap_err();
+        assert!(err.contains("Question") && err.contains(&MAX_QUESTION_LEN.to_string()));
+    }
+    
+    #[test]
+    fn option_length_limit_is_inclusive() {
+        assert!(validate_option_text(&"o".repeat(MAX_OPTION_LEN)).is_ok());
+        let err = validate_option_text(&"o".repeat(MAX_OPTION_LEN + 1)).unwrap_err();
+        assert!(err.contains("Option text") && err.contains(&MAX_OPTION_LEN.to_string()));
+    }
+    
+    // synth-125
+    
+    fn state(current_poll_id: u64, state: &str) -> PresentationState {
+        PresentationState {
+            id: 0,
+            current_poll_id,
+            state: state.to_string(),
+            reveal_at: None,
+        }
+    }
+    
+    #[test]
+    fn live_view_carries_the_active_poll() {
+        let live = Poll { is_active: true, ..poll(1) };
+        let view = live_view("a".to_string(), state(1, "voting"), Some(live), vec![(option(10, 1), 4)], 0);
+        assert_eq!((view.poll_id, view.is_active, view.state.as_str()), (Some(1), true, "voting"));
+        assert_eq!(view.options.iter().map(|o| (o.option_id, o.vote_count)).collect::<Vec<_>>(), vec![(10, 4)]);
+    }
+    
+    #[test]
+    fn live_view_signals_no_active_poll() {
+        let view = live_view("a".to_string(), state(0, "waiting"), None, Vec::new(), 0);
+        assert_eq!((view.poll_id, view.is_active), (None, false));
+        assert!(view.question.is_none() && view.options.is_empty());
+    }
+    
+    // synth-126
+    
+    #[test]
+    fn write_ins_reuse_options_with_the_same_text() {
+        let options = vec![text_option(1, 1, "Pizza"), text_option(2, 1, "Tacos")];
+        assert_eq!(existing_write_in(&options, "  pIZZA ", "a"), Ok(Some(1)));
+        assert_eq!(existing_write_in(&options, "Sushi", "a"), Ok(None));
+    }
+    
+    #[test]
+    fn write_ins_are_capped_per_user() {
+        let options = (1..=MAX_WRITE_INS_PER_USER as u64)
+            .map(|id| PollOption { added_by: Some("a".to_string()), ..text_option(id, 1, &format!("Idea {}", id)) })
+            .collect::<Vec<_>>();
+        assert!(existing_write_in(&options, "One more", "a").is_err());
+        assert_eq!(existing_write_in(&options, "One more", "b"), Ok(None));
+        // Picking an existing text doesn't count against the limit
+        assert_eq!(existing_write_in(&options, "Idea 1", "a"), Ok(Some(1)));
+    }
+    
+    // synth-127
+    
+    #[test]
+    fn unanimous_vote_has_zero_entropy() {
+        assert_eq!(consensus_scores(&[7, 0, 0]), (0.0, Some(1.0)));
+    }
+    
+    #[test]
+    fn even_split_has_full_entropy() {
+        assert_eq!(consensus_scores(&[5, 5]), (1.0, Some(0.0)));
+        assert_eq!(consensus_scores(&[0, 0]), (0.0, None));
+    }
+    
+    // synth-128
+    
+    #[test]
+    fn results_split_by_source() {
+        let from = |vote_id, source: &str, option_id| Vote { source: Some(source.to_string()), ..vote(vote_id, 1, "u", option_id, 0) };
+        let votes = vec![
+            from(1, "web", 10),
+            from(2, "kiosk", 10),
+            from(3, "web", 10),
+            from(4, "web", 11),
+            Vote { voided: true, ..from(5, "kiosk", 11) },
+            vote(6, 1, "u", 11, 0),
+        ];
+        assert_eq!(source_counts(&votes), vec![
+            ("kiosk".to_string(), 10, 1),
+            ("unknown".to_string(), 11, 1),
+            ("web".to_string(), 10, 2),
+            ("web".to_string(), 11, 1),
+        ]);
+    }
+    
+    #[test]
+    fn unknown_sources_are_rejected() {
+        assert!(validate_source(&Some("kiosk".to_string())).is_ok());
+        assert!(validate_source(&None).is_ok());
+        assert!(validate_source(&Some("fax".to_string())).is_err());
+    }
+    
+    // synth-129
+    
+    #[test]
+    fn rapid_second_poll_creation_is_rejected() {
+        let cooldown = default_settings().poll_cooldown_millis;
+        let admin = User { last_created_poll_at: Some(10 * SEC), ..user("host", "admin") };
+        let err = check_poll_cooldown(&admin, 10 * SEC + 500_000, cooldown).unwrap_err();
+        assert!(err.starts_with(E_RATE_LIMITED));
+        assert!(check_poll_cooldown(&admin, 12 * SEC, cooldown).is_ok());
+        assert!(check_poll_cooldown(&user("host", "admin"), 0, cooldown).is_ok());
+    }
+    
+    // synth-130
+    
+    #[test]
+    fn reconciliation_recounts_from_votes() {
+        // Whatever the stored counts drifted to, the votes say 2 and 1
+        let options = vec![option(10, 1), option(11, 1)];
+        let votes = vec![
+            vote(1, 1, "a", 10, 0),
+            vote(2, 1, "b", 10, 0),
+            vote(3, 1, "c", 11, 0),
+            Vote { voided: true, ..vote(4, 1, "d", 11, 0) },
+            Vote { provisional: true, ..vote(5, 1, "e", 10, 0) },
+        ];
+        let results = tally_results(&options, &votes).iter().map(|r| (r.option_id, r.vote_count)).collect::<Vec<_>>();
+        assert_eq!(results, vec![(10, 2), (11, 1)]);
+    }
+    
+    // synth-131
+    
+    #[test]
+    fn votes_before_the_scheduled_open_are_rejected() {
+        let scheduled = Poll { is_active: true, scheduled_open_at: Some(60 * SEC), ..poll(1) };
+        let err = voting_open_at(&scheduled, 30 * SEC, 0).unwrap_err();
+        assert_eq!(err, fail(E_CLOSED, "Voting hasn't started yet"));
+        assert!(voting_open_at(&scheduled, 60 * SEC, 0).is_ok());
+    }
+    
+    // synth-132
+    
+    #[test]
+    fn connection_durations_are_bucketed() {
+        let now = 4_000 * SEC;
+        let users = vec![
+            // Joined 30s ago, 10 min ago and 2 hours ago, still connected
+            User { connected_at: now - 30 * SEC, ..user("a", "user") },
+            User { connected_at: now - 600 * SEC, ..user("b", "user") },
+            User { connected_at: 0, ..user("c", "user") },
+            // Stayed 2 minutes before leaving
+            User { connected: false, connected_at: 0, last_seen: 120 * SEC, ..user("d", "user") },
+        ];
+        assert_eq!(duration_counts(&users, now), [1, 1, 1, 0, 0, 1]);
+    }
+    
+    // synth-133
+    
+    fn response(response_id: u64) -> TextResponse {
+        TextResponse {
+            response_id,
+            poll_id: 1,
+            user_id: "a".to_string(),
+            text: "Hello".to_string(),
+            submitted_at: 0,
+            flag_count: 0,
+            hidden: false,
+        }
+    }
+    
+    #[test]
+    fn enough_flags_hide_a_response() {
+        let mut flagged = response(1);
+        for _ in 1..FLAG_HIDE_THRESHOLD {
+            flagged = with_flag(flagged);
+            assert!(!flagged.hidden);
+        }
+        flagged = with_flag(flagged);
+        assert!(flagged.hidden);
+        assert_eq!(flagged.flag_count, FLAG_HIDE_THRESHOLD);
+    }
+    
+    #[test]
+    fn admins_can_hide_and_unhide_regardless_of_flags() {
+        let hidden = with_hidden(response(1), true);
+        assert!(hidden.hidden);
+        let restored = with_hidden(TextResponse { flag_count: FLAG_HIDE_THRESHOLD, ..hidden }, false);
+        assert_eq!((restored.hidden, restored.flag_count), (false, 0));
+    }
+    
+    // synth-134
+    
+    #[test]
+    fn timing_stats_report_median_and_fastest_correct_voter() {
+        let quiz = Poll { correct_option_id: Some(10), ..poll(1) };
+        let start = 100 * SEC;
+        let votes = vec![
+            vote(1, 1, "slow", 10, start + 9 * SEC),
+            vote(2, 1, "wrong", 11, start + SEC),
+            vote(3, 1, "quick", 10, start + 3 * SEC),
+            vote(4, 1, "mid", 11, start + 5 * SEC),
+            // Cast before this activation, so not timed
+            vote(5, 1, "stale", 10, start - SEC),
+        ];
+        let stat = timing_stat(&quiz, start, &votes, 0);
+        assert_eq!(stat.timed_votes, 4);
+        assert_eq!(stat.median_micros, Some(4 * SEC));
+        assert_eq!(stat.fastest_user_id.as_deref(), Some("quick"));
+        assert_eq!(stat.fastest_micros, Some(3 * SEC));
+    }
+    
+    // synth-135
+    
+    #[test]
+    fn deactivate_all_closes_every_active_poll_once() {
+        let polls = vec![
+            Poll { is_active: true, ..poll(1) },
+            Poll { is_active: true, ..poll(2) },
+            Poll { closed_at: Some(SEC), ..poll(3) },
+        ];
+        let closed = close_active(polls, 5 * SEC);
+        assert_eq!(closed.iter().map(|p| (p.poll_id, p.is_active, p.closed_at)).collect::<Vec<_>>(),
+            vec![(1, false, Some(5 * SEC)), (2, false, Some(5 * SEC))]);
+        assert!(close_active(closed, 6 * SEC).is_empty());
+    }
+    
+    // synth-136
+    
+    #[test]
+    fn changing_a_vote_twice_leaves_three_revisions() {
+        let submissions = [(10, SEC), (11, 2 * SEC), (10, 3 * SEC)];
+        let revisions = submissions.iter()
+            .filter_map(|(option_id, at)| vote_revision_for(&poll(1), "a".to_string(), *option_id, *at))
+            .collect::<Vec<_>>();
+        assert_eq!(revisions.iter().map(|r| (r.option_id, r.changed_at)).collect::<Vec<_>>(), submissions.to_vec());
+        
+        let secret = Poll { anonymous_reports: true, ..poll(2) };
+        assert!(vote_revision_for(&secret, "a".to_string(), 10, SEC).is_none());
+    }
+    
+    // synth-137
+    
+    #[test]
+    fn full_poll_turns_away_the_next_new_voter() {
+        let giveaway = Poll { max_votes: Some(2), ..poll(1) };
+        let mut votes = vec![vote(1, 1, "a", 10, 0)];
+        assert!(check_capacity(&giveaway, &votes).is_ok());
+        votes.push(Vote { provisional: true, ..vote(2, 1, "b", 10, 0) });
+        assert_eq!(check_capacity(&giveaway, &votes), Err(fail(E_CLOSED, "Voting is full")));
+        // A voided vote frees its place
+        votes[0].voided = true;
+        assert!(check_capacity(&giveaway, &votes).is_ok());
+    }
+    
+    // synth-138
+    
+    #[test]
+    fn answer_matrix_fills_cells_per_user_and_poll() {
+        let votes = vec![
+            vote(1, 1, "a", 10, 0),
+            vote(2, 2, "a", 20, 0),
+            vote(3, 2, "b", 21, 0),
+            Vote { is_demo: true, ..vote(4, 1, "demo", 10, 0) },
+        ];
+        let rows = matrix_rows(&[1, 2], &votes, vec!["c".to_string()]).unwrap();
+        let cells = rows.iter().map(|r| (r.user_id.as_str(), r.option_ids.clone())).collect::<Vec<_>>();
+        assert_eq!(cells, vec![
+            ("a", vec![Some(10), Some(20)]),
+            ("b", vec![None, Some(21)]),
+            ("c", vec![None, None]),
+        ]);
+    }
+    
+    #[test]
+    fn answer_matrix_ignores_repeated_poll_ids() {
+        let polls = || vec![Poll { created_at: 5 * SEC, ..poll(1) }, poll(2), Poll { anonymous_reports: true, ..poll(3) }].into_iter();
+        let ids = |picked: Result<Vec<Poll>, String>| picked.unwrap().iter().map(|p| p.poll_id).collect::<Vec<_>>();
+        assert_eq!(ids(matrix_polls(polls(), vec![1, 2, 1])), vec![2, 1]);
+        assert_eq!(ids(matrix_polls(polls(), vec![])), vec![2, 1]);
+        assert!(matrix_polls(polls(), vec![3, 3]).unwrap_err().starts_with(E_NOT_FOUND));
+    }
+    
+    // synth-139
+    
+    #[test]
+    fn stale_polls_are_old_inactive_and_unvoted() {
+        let now = 10_000 * SEC;
+        let stale = |p: Poll, votes| stale_entry(p, votes, now, 3_600).map(|s| (s.poll_id, s.age_secs));
+        assert_eq!(stale(Poll { created_at: 0, ..poll(1) }, 0), Some((1, 10_000)));
+        assert_eq!(stale(Poll { created_at: 0, ..poll(2) }, 4), None);
+        assert_eq!(stale(Poll { created_at: 0, is_active: true, ..poll(3) }, 0), None);
+        assert_eq!(stale(Poll { created_at: now - 60 * SEC, ..poll(4) }, 0), None);
+    }
+    
+    // synth-140
+    
+    #[test]
+    fn migration_seeds_one_state_per_session() {
+        let users = vec![
+            User { session_id: "room-a".to_string(), ..user("a", "user") },
+            User { session_id: "room-b".to_string(), ..user("b", "user") },
+            User { session_id: "room-a".to_string(), ..user("c", "admin") },
+        ];
+        let sessions = seeded_sessions(&state(4, "voting"), &users);
+        let rows = sessions.iter()
+            .map(|s| (s.session_id.as_str(), s.current_poll_id, s.state.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(rows, vec![("room-a", 4, "voting"), ("room-b", 4, "voting")]);
+    }
+    
+    // synth-141
+    
+    #[test]
+    fn wald_interval_matches_the_formula() {
+        // p = 0.5 over 100 votes: margin = 1.96 * sqrt(0.25 / 100) = 0.098
+        let (p, lower, upper) = wald_interval(50, 100);
+        assert_eq!(p, Some(0.5));
+        assert!((lower.unwrap() - 0.402).abs() < 1e-9);
+        assert!((upper.unwrap() - 0.598).abs() < 1e-9);
+    }
+    
+    #[test]
+    fn small_and_empty_samples_get_wide_or_no_intervals() {
+        assert_eq!(wald_interval(2, 4), (Some(0.5), Some(0.0), Some(1.0)));
+        assert_eq!(wald_interval(0, 0), (None, None, None));
+    }
+    
+    // synth-142
+    
+    #[test]
+    fn template_keeps_questions_and_options_in_order() {
+        let polls = vec![
+            Poll { question: "Second".to_string(), ..poll(2) },
+            Poll { question: "First".to_string(), ..poll(1) },
+            Poll { archived: true, ..poll(3) },
+        ];
+        let options = vec![text_option(12, 2, "B"), text_option(11, 2, "A"), text_option(10, 1, "Yes"), text_option(13, 3, "Old")];
+        let saved = template_polls(polls, &options);
+        let shape = saved.iter()
+            .map(|p| (p.question.as_str(), p.options.iter().map(|o| o.text.as_str()).collect::<Vec<_>>()))
+            .collect::<Vec<_>>();
+        assert_eq!(shape, vec![("First", vec!["Yes"]), ("Second", vec!["A", "B"])]);
+        
+        let template = PollTemplate { name: "deck".to_string(), polls: saved, saved_at: 0 };
+        assert!(validate_template(&template).is_ok());
+    }
+    
+    #[test]
+    fn invalid_template_entries_are_caught_before_instantiating() {
+        let template = PollTemplate {
+            name: "deck".to_string(),
+            polls: vec![TemplatePoll { question: "q".repeat(MAX_QUESTION_LEN + 1), options: Vec::new() }],
+            saved_at: 0,
+        };
+        assert!(validate_template(&template).is_err());
+        assert!(validate_template_name("bad/name").is_err());
+    }
+    
+    // synth-143
+    
+    #[test]
+    fn queue_dequeues_in_insertion_order() {
+        let polls = [poll(1), poll(2), Poll { archived: true, ..poll(3) }, poll(4)];
+        let find = |poll_id| polls.iter().find(|p| p.poll_id == poll_id).cloned();
+        // Queued as 4, 3, 9 (deleted since), 1, 2
+        let mut queue = [4, 3, 9, 1, 2].iter().enumerate()
+            .map(|(i, poll_id)| PollQueue { queue_id: i as u64 + 1, poll_id: *poll_id })
+            .collect::<Vec<_>>();
+        let mut presented = Vec::new();
+        loop {
+            let (popped, next) = pop_queue(queue.clone(), find);
+            queue.retain(|q| !popped.contains(&q.queue_id));
+            match next {
+                Some(poll) => presented.push(poll.poll_id),
+                None => break,
+            }
+        }
+        assert_eq!(presented, vec![4, 1, 2]);
+        assert!(queue.is_empty());
+    }
+    
+    // synth-144
+    
+    #[test]
+    fn creator_is_blocked_only_when_configured() {
+        let proposal = Poll { created_by: Some("alice".to_string()), ..poll(1) };
+        assert!(check_creator_vote(&proposal, "alice").is_ok());
+        let proposal = Poll { creator_can_vote: false, ..proposal };
+        assert_eq!(check_creator_vote(&proposal, "alice"), Err(fail(E_FORBIDDEN, "Poll creator cannot vote")));
+        assert!(check_creator_vote(&proposal, "bob").is_ok());
+    }
+    
+    // synth-145
+    
+    #[test]
+    fn group_results_sum_across_polls_by_text() {
+        let round_one = vec![(text_option(1, 1, "Red"), 3), (text_option(2, 1, "Blue"), 2)];
+        let round_two = vec![(text_option(3, 2, "blue"), 4), (text_option(4, 2, "Green"), 1)];
+        assert_eq!(group_tally(vec![round_one, round_two]), vec![
+            ("Red".to_string(), 3, 1),
+            ("Blue".to_string(), 6, 2),
+            ("Green".to_string(), 1, 1),
+        ]);
+    }
+    
+    // synth-146
+    
+    #[test]
+    fn role_switch_needs_debug_mode() {
+        let err = check_role_switch(&default_settings(), "admin").unwrap_err();
+        assert!(err.starts_with(E_FORBIDDEN));
+        let debug = Settings { debug_mode: true, ..default_settings() };
+        assert!(check_role_switch(&debug, "admin").is_ok());
+        assert!(check_role_switch(&debug, "root").is_err());
+    }
+    
+    // synth-147
+    
+    #[test]
+    fn leader_is_locked_in_when_no_one_can_catch_up() {
+        let counts = vec![(option(1, 1), 3), (option(2, 1), 9), (option(3, 1), 4)];
+        let locked = projection(1, counts.clone(), 4, 0);
+        assert_eq!((locked.leader_option_id, locked.decided), (Some(2), true));
+        assert_eq!(locked.contenders, vec![2]);
+        
+        let open = projection(1, counts, 5, 0);
+        assert!(!open.decided);
+        assert_eq!(open.contenders, vec![2, 3]);
+    }
+    
+    // synth-148
+    
+    #[test]
+    fn polls_cannot_open_before_their_prerequisite_completes() {
+        let branch = Poll { requires_poll_id: Some(1), ..poll(2) };
+        let err = prerequisite_met(&branch, |_| Some(poll(1))).unwrap_err();
+        assert_eq!(err, fail(E_CONFLICT, "Prerequisite poll not completed"));
+        assert!(prerequisite_met(&branch, |_| Some(Poll { is_active: true, activated_at: Some(0), ..poll(1) })).is_err());
+        assert!(prerequisite_met(&branch, |_| Some(Poll { activated_at: Some(0), ..poll(1) })).is_ok());
+    }
+    
+    #[test]
+    fn prerequisite_cycles_are_rejected() {
+        let polls = [poll(1), Poll { requires_poll_id: Some(1), ..poll(2) }, Poll { requires_poll_id: Some(2), ..poll(3) }];
+        let find = |id| polls.iter().find(|p| p.poll_id == id).cloned();
+        assert!(check_prerequisite_chain(1, Some(3), find).is_err());
+        assert!(check_prerequisite_chain(1, Some(1), find).is_err());
+        assert!(check_prerequisite_chain(3, Some(1), find).is_ok());
+    }
+    
+    // synth-149
+    
+    #[test]
+    fn long_export_has_one_row_per_vote() {
+        let votes = vec![vote(2, 1, "b", 11, 2 * SEC), vote(1, 1, "a", 10, SEC), vote(3, 1, "c", 10, 3 * SEC)];
+        let options = vec![text_option(10, 1, "Yes"), text_option(11, 1, "No")];
+        let rows = long_rows(&poll(1), votes.clone(), &options);
+        assert_eq!(rows.len(), votes.len());
+        assert_eq!((rows[0].user_id.as_deref(), rows[0].option_text.as_str()), (Some("a"), "Yes"));
+        
+        let secret = Poll { anonymous_reports: true, ..poll(1) };
+        assert!(long_rows(&secret, votes, &options).iter().all(|r| r.user_id.is_none()));
+        assert!(long_rows(&poll(1), Vec::new(), &options).is_empty());
+    }
+    
+    // synth-150
+    
+    #[test]
+    fn identically_worded_polls_are_flagged() {
+        let asked = |poll_id, question: &str| Poll { question: question.to_string(), ..poll(poll_id) };
+        let polls = vec![asked(3, "Lunch?"), asked(1, "Favourite colour?"), asked(2, " favourite COLOUR? ")];
+        assert_eq!(duplicate_groups(polls), vec![("favourite colour?".to_string(), vec![1, 2])]);
+    }
+    
+    // synth-151
+    
+    #[test]
+    fn late_votes_land_only_within_the_grace_window() {
+        let closed = Poll { closed_at: Some(100 * SEC), ..poll(1) };
+        assert!(voting_open_at(&closed, 104 * SEC, 5).is_ok());
+        assert_eq!(voting_open_at(&closed, 105 * SEC, 5), Err(fail(E_CLOSED, "Poll is not active")));
+        assert!(voting_open_at(&closed, 101 * SEC, 0).is_err());
+        // Archiving ends the grace period early
+        assert!(voting_open_at(&Poll { archived: true, ..closed }, 101 * SEC, 5).is_err());
+    }
+    
+    // synth-152
+    
+    #[test]
+    fn margin_is_winner_minus_runner_up() {
+        let counts = vec![(option(1, 1), 12), (option(2, 1), 15), (option(3, 1), 4)];
+        assert_eq!(podium(&counts), (Some(2), Some(1), 3));
+    }
+    
+    #[test]
+    fn margin_handles_ties_single_options_and_no_votes() {
+        assert_eq!(podium(&[(option(1, 1), 5), (option(2, 1), 5)]), (Some(1), Some(2), 0));
+        assert_eq!(podium(&[(option(1, 1), 5)]), (Some(1), None, 5));
+        assert_eq!(podium(&[(option(1, 1), 0), (option(2, 1), 0)]), (None, None, 0));
+    }
+    
+    // synth-153
+    
+    #[test]
+    fn vote_changes_stop_once_the_budget_is_spent() {
+        let capped = Poll { max_changes: Some(2), ..poll(1) };
+        let mut current = vote(1, 1, "a", 10, 0);
+        for option_id in [11, 10] {
+            assert_eq!(check_vote_change(&capped, &current, option_id, false), Ok(true));
+            current = Vote { option_id, change_count: current.change_count + 1, ..current };
+        }
+        assert_eq!(check_vote_change(&capped, &current, 11, false), Err(fail(E_CONFLICT, "Maximum vote changes reached")));
+        // Resubmitting the same choice isn't a change
+        assert_eq!(check_vote_change(&capped, &current, 10, false), Ok(false));
+    }
+    
+    // synth-154
+    
+    #[test]
+    fn options_without_votes_are_flagged() {
+        let counts = vec![(option(1, 1), 4), (option(2, 1), 0), (option(3, 1), 1)];
+        let dead = unpicked_options(counts);
+        assert_eq!(dead.iter().map(|d| (d.option_id, d.poll_id)).collect::<Vec<_>>(), vec![(2, 1)]);
+    }
+    
+    // synth-155
+    
+    #[test]
+    fn switching_sessions_is_rejected_or_clears_votes() {
+        let member = User { session_id: "Room-A".to_string(), ..user("a", "user") };
+        assert_eq!(check_session_switch(&member, "room-a", &default_settings()), Ok(false));
+        assert_eq!(check_session_switch(&member, "room-b", &default_settings()),
+            Err(fail(E_CONFLICT, "Already joined a different session")));
+        let clearing = Settings { clear_votes_on_session_switch: true, ..default_settings() };
+        assert_eq!(check_session_switch(&member, "room-b", &clearing), Ok(true));
+    }
+    
+    // synth-156
+    
+    #[test]
+    fn deltas_count_only_votes_after_the_cutoff() {
+        let options = vec![option(10, 1), option(11, 1)];
+        let votes = vec![vote(1, 1, "a", 10, SEC), vote(2, 1, "b", 10, 5 * SEC), vote(3, 1, "c", 11, 6 * SEC)];
+        let gained = |since| deltas_since(&options, &votes, since).iter().map(|d| d.votes_gained).collect::<Vec<_>>();
+        assert_eq!(gained(2 * SEC), vec![1, 1]);
+        assert_eq!(gained(0), vec![2, 1]);
+        assert_eq!(gained(100 * SEC), vec![0, 0]);
+    }
+    
+    // synth-157
+    
+    #[test]
+    fn locked_session_turns_away_newcomers_until_unlocked() {
+        let locked = Settings { locked: true, ..default_settings() };
+        assert_eq!(check_admission(&locked, "user", &[]), Err(fail(E_CLOSED, "Session is locked")));
+        let unlocked = Settings { locked: false, ..locked };
+        assert!(check_admission(&unlocked, "user", &[]).is_ok());
+    }
+    
+    // synth-158
+    
+    #[test]
+    fn results_split_by_voter_role() {
+        let votes = vec![
+            vote(1, 1, "host", 10, 0),
+            vote(2, 1, "a", 11, 0),
+            vote(3, 1, "b", 11, 0),
+            vote(4, 1, "gone", 10, 0),
+        ];
+        let role_of = |user_id: &str| match user_id {
+            "host" => Some("admin".to_string()),
+            "gone" => None,
+            _ => Some("user".to_string()),
+        };
+        assert_eq!(role_counts(&votes, role_of), vec![
+            ("admin".to_string(), 10, 1),
+            ("unknown".to_string(), 10, 1),
+            ("user".to_string(), 11, 2),
+        ]);
+    }
+    
+    // synth-159
+    
+    #[test]
+    fn state_pointing_at_a_deleted_poll_heals_to_waiting() {
+        let healed = healed_presentation(state(7, "voting"), |_| false).unwrap();
+        assert_eq!((healed.current_poll_id, healed.state.as_str()), (0, "waiting"));
+        assert!(healed_presentation(state(7, "voting"), |_| true).is_none());
+        assert!(healed_presentation(state(0, "waiting"), |_| false).is_none());
+        
+        let session = SessionState { session_id: "room".to_string(), current_poll_id: 7, state: "results".to_string() };
+        let healed = healed_session(session, |poll_id| poll_id != 7).unwrap();
+        assert_eq!((healed.current_poll_id, healed.state.as_str()), (0, "waiting"));
+    }
+    
+    // synth-160
+    
+    #[test]
+    fn abstention_reasons_are_stored_and_listed() {
+        let reason = validate_abstain_reason(Some("  Not my area ".to_string())).unwrap();
+        let abstention = Abstention { abstention_id: 4, poll_id: 1, user_id: "a".to_string(), reason, abstained_at: SEC };
+        let row = reason_row(abstention.clone()).unwrap();
+        assert_eq!((row.abstention_id, row.reason.as_str(), row.abstained_at), (4, "Not my area", SEC));
+        
+        assert!(reason_row(Abstention { reason: validate_abstain_reason(Some(" ".to_string())).unwrap(), ..abstention }).is_none());
+        assert!(validate_abstain_reason(Some("r".repeat(MAX_ABSTAIN_REASON_LEN + 1))).is_err());
+    }
+    
+    #[test]
+    fn abstaining_blocks_a_later_vote() {
+        let abstention = Abstention { abstention_id: 1, poll_id: 1, user_id: "a".to_string(), reason: None, abstained_at: SEC };
+        let err = check_not_abstained(vec![abstention.clone()].into_iter(), "a").unwrap_err();
+        assert!(err.starts_with(E_CONFLICT));
+        assert!(check_not_abstained(vec![abstention].into_iter(), "b").is_ok());
+    }
+    
+    // synth-161
+    
+    #[test]
+    fn role_restricted_polls_are_hidden_from_other_roles() {
+        let staff_only = Poll { visible_to_role: Some("admin".to_string()), ..poll(1) };
+        assert!(!poll_visible_to(&staff_only, &user("a", "user")));
+        assert!(poll_visible_to(&staff_only, &user("host", "admin")));
+        assert!(poll_visible_to(&poll(2), &user("a", "user")));
+        // The same check gates voting and open-poll listings
+        let open = [Poll { is_active: true, ..staff_only }, Poll { is_active: true, ..poll(2) }];
+        assert_eq!(unvoted_poll_ids(&open, &[], &user("a", "user")), vec![2]);
+    }
+    
+    // synth-162
+    
+    #[test]
+    fn streak_counts_consecutive_polls_voted_in() {
+        let polls = (1..=4).map(poll).collect::<Vec<_>>();
+        let votes = vec![
+            vote(1, 1, "a", 10, 0),
+            vote(2, 2, "a", 20, 0),
+            vote(3, 4, "a", 40, 0),
+            Vote { voided: true, ..vote(4, 3, "a", 30, 0) },
+            vote(5, 3, "b", 30, 0),
+        ];
+        let streaks = longest_streaks(polls, &votes, vec!["a".to_string(), "b".to_string(), "c".to_string()]);
+        let found = streaks.iter().map(|s| (s.user_id.as_str(), s.longest_streak)).collect::<Vec<_>>();
+        assert_eq!(found, vec![("a", 2), ("b", 1), ("c", 0)]);
+    }
+    
+    // synth-163
+    
+    #[test]
+    fn drafts_cannot_be_activated_until_published() {
+        let draft = Poll { is_draft: true, ..poll(1) };
+        assert_eq!(check_activatable(&draft), Err(fail(E_CONFLICT, "Poll is a draft; publish it first")));
+        assert!(check_activatable(&Poll { is_draft: false, ..draft.clone() }).is_ok());
+        // Drafts stay hidden from participants and out of the running order
+        assert!(!poll_visible_to(&draft, &user("a", "user")));
+        assert!(next_in_order(&[draft], None).is_none());
+    }
+    
+    // synth-164
+    
+    #[test]
+    fn bulk_promotion_changes_only_the_source_role() {
+        let users = vec![user("a", "user"), user("host", "admin"), user("b", "user")];
+        let promoted = with_role_changed(users, "user", "admin");
+        let roles = promoted.iter().map(|u| (u.user_id.as_str(), u.role.as_str())).collect::<Vec<_>>();
+        assert_eq!(roles, vec![("a", "admin"), ("b", "admin")]);
+    }
+    
+    #[test]
+    fn bulk_promotion_to_admin_needs_confirmation() {
+        assert!(check_bulk_role_change("user", "admin", false).is_err());
+        assert!(check_bulk_role_change("user", "admin", true).is_ok());
+        assert!(check_bulk_role_change("admin", "user", false).is_ok());
+        assert!(check_bulk_role_change("user", "user", true).is_err());
+    }
+    
+    // synth-165
+    
+    #[test]
+    fn polls_sharing_most_options_score_high_similarity() {
+        let options = [
+            text_option(1, 1, "Red"), text_option(2, 1, "Green"), text_option(3, 1, "Blue"),
+            text_option(4, 2, " red"), text_option(5, 2, "GREEN"), text_option(6, 2, "Blue"),
+            text_option(7, 2, "Yellow"),
+            text_option(8, 3, "Blue"), text_option(9, 3, "Cats"), text_option(10, 3, "Dogs"),
+            text_option(11, 4, "Nothing shared"),
+        ];
+        let matches = similar_matches(1, options.to_vec());
+        assert_eq!(matches.iter().map(|(id, _)| *id).collect::<Vec<_>>(), vec![2, 3]);
+        assert!((matches[0].1 - 0.75).abs() < 1e-9);
+        assert!((matches[1].1 - 0.2).abs() < 1e-9);
+    }
+    
+    // synth-166
+    
+    #[test]
+    fn platform_breakdown_counts_each_platform() {
+        let on = |user_id, platform: Option<&str>| User {
+            platform: platform.map(|p| p.to_string()),
+            ..user(user_id, "user")
+        };
+        let users = [on("a", Some("web")), on("b", Some("ios")), on("c", Some("web")), on("d", None)];
+        assert_eq!(platform_counts(users.into_iter()), vec![
+            ("web".to_string(), 2),
+            ("ios".to_string(), 1),
+            ("unknown".to_string(), 1),
+        ]);
+    }
+    
+    #[test]
+    fn platforms_outside_the_allowlist_are_rejected() {
+        assert!(validate_platform(&None).is_ok());
+        assert!(validate_platform(&Some("kiosk".to_string())).is_ok());
+        assert!(validate_platform(&Some("smart-fridge".to_string())).is_err());
+    }
+    
+    // synth-167
+    
+    #[test]
+    fn sealed_session_rejects_every_mutation() {
+        let sealed = Settings {
+            sealed: true,
+            ..default_settings()
+        };
+        let sealed_err = Err(fail(E_SEALED, "Session is sealed"));
+        assert!(check_not_sealed(&default_settings()).is_ok());
+        assert_eq!(check_not_sealed(&sealed), sealed_err);
+        assert_eq!(check_admission(&sealed, "user", &[]), sealed_err);
+        assert_eq!(check_admission(&sealed, "admin", &[]), sealed_err);
+        
+        let existing = user("a", "user");
+        assert_eq!(check_session_switch(&existing, "other", &sealed).map(|_| ()), sealed_err);
+        // Reconnecting to the same session is not a mutation of the archive
+        assert_eq!(check_session_switch(&existing, "default", &sealed), Ok(false));
+    }
+    
+    // synth-168
+    
+    #[test]
+    fn voided_vote_stops_counting_but_stays_in_the_table() {
+        let options = [option(10, 1), option(11, 1)];
+        let mut votes = vec![vote(1, 1, "a", 10, 0), vote(2, 1, "b", 10, 0), vote(3, 1, "c", 11, 0)];
+        
+        let (voided, delta) = with_voided(votes[0].clone(), true).unwrap();
+        assert_eq!(delta, -1);
+        votes[0] = voided;
+        assert_eq!(votes.len(), 3);
+        let counts = tally_results(&options, &votes).iter().map(|r| r.vote_count).collect::<Vec<_>>();
+        assert_eq!(counts, vec![1, 1]);
+        
+        assert!(with_voided(votes[0].clone(), true).is_err());
+        let (restored, delta) = with_voided(votes[0].clone(), false).unwrap();
+        assert_eq!((restored.voided, delta), (false, 1));
+    }
+    
+    #[test]
+    fn voiding_a_provisional_vote_moves_no_result() {
+        let pending = Vote {
+            provisional: true,
+            ..vote(1, 1, "a", 10, 0)
+        };
+        assert_eq!(with_voided(pending, true).unwrap().1, 0);
+    }
+    
+    // synth-169
+    
+    fn ballot(ranking: &[u64]) -> RankedBallot {
+        RankedBallot {
+            ballot_id: 0,
+            poll_id: 1,
+            user_id: "a".to_string(),
+            ranking: ranking.to_vec(),
+            submitted_at: 0,
+        }
+    }
+    
+    #[test]
+    fn condorcet_winner_beats_every_other_option() {
+        let ballots = [ballot(&[1, 2, 3]), ballot(&[2, 1, 3]), ballot(&[1, 3])];
+        let (pairs, winner) = pairwise_preferences(&[1, 2, 3], &ballots);
+        assert_eq!(winner, Some(1));
+        assert!(pairs.contains(&(1, 2, 2)));
+        assert!(pairs.contains(&(2, 1, 1)));
+        // Unranked options tie for last
+        assert!(pairs.contains(&(3, 2, 1)));
+    }
+    
+    #[test]
+    fn rock_paper_scissors_ballots_have_no_condorcet_winner() {
+        let ballots = [ballot(&[1, 2, 3]), ballot(&[2, 3, 1]), ballot(&[3, 1, 2])];
+        let (pairs, winner) = pairwise_preferences(&[1, 2, 3], &ballots);
+        assert_eq!(winner, None);
+        assert_eq!(pairs.len(), 6);
+    }
+    
+    // synth-170
+    
+    #[test]
+    fn one_bad_option_rejects_the_whole_batch_before_any_insert() {
+        // create_poll only calls insert_poll once the whole batch passes, so
+        // a bad last option means no poll row is written at all
+        let mut options = vec![option_input("Yes", None), option_input("No", None)];
+        assert!(validate_options(&options).is_ok());
+        options.push(option_input(&"x".repeat(MAX_OPTION_LEN + 1), None));
+        assert!(validate_options(&options).is_err());
+        
+        let too_many = (0..=MAX_OPTIONS_PER_POLL)
+            .map(|i| option_input(&format!("Option {}", i), None))
+            .collect::<Vec<_>>();
+        assert!(validate_options(&too_many).is_err());
+    }
+    
+    // synth-171
+    
+    #[test]
+    fn cleanup_archives_old_closed_polls_only() {
+        let closed = |poll_id, closed_at| Poll {
+            is_active: false,
+            closed_at: Some(closed_at),
+            ..poll(poll_id)
+        };
+        let now = 100 * SEC;
+        let polls = vec![
+            closed(1, 10 * SEC),
+            closed(2, 95 * SEC),
+            Poll { is_active: true, ..poll(3) },
+            Poll { archived: true, ..closed(4, 10 * SEC) },
+        ];
+        let expired = expired_polls(polls, now, 60);
+        assert_eq!(expired.iter().map(|p| p.poll_id).collect::<Vec<_>>(), vec![1]);
+        assert!(expired[0].archived);
+    }
+    
+    // synth-172
+    
+    #[test]
+    fn resolve_option_failure_modes_map_to_stable_codes() {
+        // submit_vote, void_vote and set_correct_answer all resolve through this
+        let cases = [
+            (None, 1, Some(E_NOT_FOUND)),
+            (Some(option(5, 2)), 1, Some(E_INVALID_INPUT)),
+            (Some(option(5, 1)), 1, None),
+        ];
+        for (found, poll_id, code) in cases {
+            match (option_for_poll(found, poll_id), code) {
+                (Ok(option), None) => assert_eq!(option.poll_id, poll_id),
+                (Err(err), Some(code)) => assert!(err.starts_with(code), "{}", err),
+                (result, code) => panic!("unexpected {:?} for {:?}", result, code),
+            }
+        }
+    }
+    
+    // synth-173
+    
+    #[test]
+    fn skewed_distribution_has_high_concentration() {
+        assert!((gini_coefficient(&[10, 0, 0, 0]) - 0.75).abs() < 1e-9);
+        assert!((gini_coefficient(&[6, 2]) - 0.25).abs() < 1e-9);
+        assert_eq!(gini_coefficient(&[5, 5, 5]), 0.0);
+    }
+    
+    #[test]
+    fn concentration_is_zero_without_votes_or_rivals() {
+        assert_eq!(gini_coefficient(&[0, 0]), 0.0);
+        assert_eq!(gini_coefficient(&[7]), 0.0);
+        assert_eq!(gini_coefficient(&[]), 0.0);
+    }
+    
+    // synth-174
+    
+    #[test]
+    fn merging_options_combines_counts_and_dedupes_voters() {
+        // "b" backs both options; multi-vote polls allow that
+        let votes = vec![
+            vote(1, 1, "a", 10, 0),
+            vote(2, 1, "b", 10, 0),
+            vote(3, 1, "b", 11, 0),
+            vote(4, 1, "c", 11, 0),
+            vote(5, 1, "d", 12, 0),
+        ];
+        let (duplicates, moved) = merged_votes(&votes, 10, 11);
+        assert_eq!(duplicates, vec![3]);
+        assert_eq!(moved.iter().map(|v| (v.vote_id, v.option_id)).collect::<Vec<_>>(), vec![(4, 10)]);
+        
+        let merged = votes.into_iter()
+            .filter(|v| !duplicates.contains(&v.vote_id))
+            .map(|v| moved.iter().find(|m| m.vote_id == v.vote_id).cloned().unwrap_or(v))
+            .collect::<Vec<_>>();
+        let counts = tally_results(&[option(10, 1), option(12, 1)], &merged).iter()
+            .map(|r| (r.option_id, r.vote_count))
+            .collect::<Vec<_>>();
+        assert_eq!(counts, vec![(10, 3), (12, 1)]);
+    }
+    
+    #[test]
+    fn merging_options_keeps_the_higher_rank() {
+        assert_eq!(merged_ranking(&[12, 11, 10], 10, 11), vec![12, 10]);
+        assert_eq!(merged_ranking(&[10, 12, 11], 10, 11), vec![10, 12]);
+    }
+    
+    // synth-175
+    
+    #[test]
+    fn server_time_is_close_to_the_wall_clock() {
+        let before = Timestamp::from(std::time::SystemTime::now()).to_micros_since_unix_epoch();
+        let row = server_time_row("a".to_string(), Timestamp::from(std::time::SystemTime::now()));
+        assert_eq!(row.user_id, "a");
+        assert!(row.server_micros >= before);
+        assert!(row.server_micros - before < SEC);
+    }
+    
+    // synth-176
+    
+    #[test]
+    fn session_ids_are_normalized_to_lowercase() {
+        assert_eq!(normalize_session_id(" Room-A_1 "), Ok("room-a_1".to_string()));
     }
     
-    // Create or update user
-    let user = User {
-        user_id: ctx.sender.to_string(),
-        session_id,
-        role,
-        connected_at: ctx.timestamp.to_micros_since_unix_epoch(),
-    };
+    #[test]
+    fn empty_long_or_odd_session_ids_are_rejected() {
+        let invalid = Err(fail(E_INVALID_INPUT, "Invalid session id"));
+        assert_eq!(normalize_session_id(""), invalid);
+        assert_eq!(normalize_session_id("   "), invalid);
+        assert_eq!(normalize_session_id(&"a".repeat(MAX_SESSION_ID_LEN + 1)), invalid);
+        assert!(normalize_session_id(&"a".repeat(MAX_SESSION_ID_LEN)).is_ok());
+        assert_eq!(normalize_session_id("room/1"), invalid);
+        assert_eq!(normalize_session_id("räum"), invalid);
+    }
     
-    // Get user table handle
-    let user_table = ctx.db.user();
+    // synth-177
     
-    // Check if user exists
-    if user_table.user_id().find(&user.user_id).is_some() {
-        // Update user
-        user_table.user_id().update(user);
-    } else {
-        // Insert new user
-        user_table.insert(user);
+    #[test]
+    fn turnout_curve_never_decreases() {
+        let vote_times = [25 * SEC, 5 * SEC, 12 * SEC, 12 * SEC, 31 * SEC];
+        let points = turnout_points(1, 0, 40 * SEC, 10, &vote_times, 8).unwrap();
+        let cumulative = points.iter().map(|p| p.cumulative_votes).collect::<Vec<_>>();
+        assert_eq!(cumulative, vec![1, 3, 4, 5, 5]);
+        assert!(points.windows(2).all(|w| w[0].turnout_percent <= w[1].turnout_percent));
+        assert_eq!(points.last().map(|p| p.turnout_percent), Some(62.5));
     }
     
-    // Initialize presentation state if it doesn't exist
-    let presentation_table = ctx.db.presentation_state();
-    if presentation_table.id().find(&0).is_none() {
-        let initial_state = PresentationState {
-            id: 0,
-            current_poll_id: 0,
-            state: "waiting".to_string(),
+    #[test]
+    fn turnout_curve_caps_the_bucket_count() {
+        let end = (MAX_TIMELINE_BUCKETS + 1) * SEC;
+        assert!(turnout_points(1, 0, end, 1, &[], 0).is_err());
+    }
+    
+    // synth-178
+    
+    #[test]
+    fn votes_are_only_accepted_inside_the_window() {
+        let windowed = Poll {
+            is_active: true,
+            window_start: Some(10 * SEC),
+            window_end: Some(20 * SEC),
+            ..poll(1)
         };
-        presentation_table.insert(initial_state);
+        let outside = Err(fail(E_CLOSED, "Outside the voting window"));
+        assert_eq!(voting_open_at(&windowed, 9 * SEC, 0), outside);
+        assert!(voting_open_at(&windowed, 10 * SEC, 0).is_ok());
+        assert!(voting_open_at(&windowed, 15 * SEC, 0).is_ok());
+        assert_eq!(voting_open_at(&windowed, 20 * SEC, 0), outside);
     }
     
-    Ok(())
-}
-
-#[reducer]
-pub fn create_poll(ctx: &ReducerContext, question: String, options: Vec<String>) -> Result<(), String> {
-    // Check if user is admin
-    let user_table = ctx.db.user();
-    if let Some(user) = user_table.user_id().find(&ctx.sender.to_string()) {
-        if user.role != "admin" {
-            return Err("Only admins can create polls".to_string());
-        }
-    } else {
-        return Err("User not found".to_string());
+    #[test]
+    fn the_window_applies_even_to_an_active_poll() {
+        let windowed = Poll {
+            is_active: true,
+            window_end: Some(20 * SEC),
+            ..poll(1)
+        };
+        assert!(voting_open_at(&windowed, 25 * SEC, 0).is_err());
     }
     
-    // Generate poll ID
-    let poll_table = ctx.db.poll();
-    let polls = poll_table.iter().collect::<Vec<_>>();
-    let poll_id = (polls.len() + 1) as u64;
+    #[test]
+    fn voting_window_must_start_before_it_ends() {
+        assert!(validate_window(10, 20).is_ok());
+        assert!(validate_window(20, 20).is_err());
+        assert!(validate_window(30, 20).is_err());
+    }
     
-    // Create poll
-    let poll = Poll {
-        poll_id,
-        question,
-        is_active: false, // Not active until explicitly activated
-        created_at: ctx.timestamp.to_micros_since_unix_epoch(),
-    };
+    // synth-179
     
-    poll_table.insert(poll);
+    #[test]
+    fn control_characters_are_rejected_in_poll_text() {
+        let invalid = Err(fail(E_INVALID_INPUT, "Text contains invalid characters"));
+        assert_eq!(validate_question("Best\u{0000}colour?"), invalid);
+        assert_eq!(validate_question("Line one\nline two"), invalid);
+        assert_eq!(validate_option_text("Tab\there"), invalid);
+    }
     
-    // Create options
-    let option_table = ctx.db.poll_option();
-    for (i, option_text) in options.iter().enumerate() {
-        let option = PollOption {
-            option_id: (i + 1) as u64,
-            poll_id,
-            text: option_text.clone(),
+    #[test]
+    fn ordinary_unicode_is_allowed_in_poll_text() {
+        assert!(validate_question("¿Qué color prefieres? 🎨").is_ok());
+        assert!(validate_option_text("Grün").is_ok());
+    }
+    
+    // synth-180
+    
+    #[test]
+    fn pacing_averages_the_gaps_between_activations() {
+        let activated = |poll_id, at| Poll {
+            activated_at: Some(at),
+            ..poll(poll_id)
         };
-        option_table.insert(option);
+        let polls = [activated(1, 100 * SEC), activated(2, 10 * SEC), activated(3, 40 * SEC), poll(4)];
+        let stats = pacing(polls.into_iter(), 0);
+        assert_eq!(stats.activation_count, 3);
+        assert_eq!(stats.average_gap_micros, Some(45 * SEC));
+        assert_eq!(stats.max_gap_micros, Some(60 * SEC));
     }
     
-    // Return success - the poll_id has been stored in the database
-    Ok(())
-}
-
-#[reducer]
-pub fn activate_poll(ctx: &ReducerContext, poll_id: u64) -> Result<(), String> {
-    // Check if user is admin
-    let user_table = ctx.db.user();
-    if let Some(user) = user_table.user_id().find(&ctx.sender.to_string()) {
-        if user.role != "admin" {
-            return Err("Only admins can activate polls".to_string());
-        }
-    } else {
-        return Err("User not found".to_string());
+    #[test]
+    fn pacing_has_no_gaps_with_one_activation() {
+        let polls = [Poll { activated_at: Some(SEC), ..poll(1) }];
+        let stats = pacing(polls.into_iter(), 0);
+        assert_eq!((stats.activation_count, stats.average_gap_micros, stats.max_gap_micros), (1, None, None));
     }
     
-    // Check if poll exists
-    let poll_table = ctx.db.poll();
-    if let Some(poll) = poll_table.poll_id().find(&poll_id) {
-        // Deactivate all other polls
-        for p in poll_table.iter() {
-            if p.poll_id != poll_id && p.is_active {
-                // Update poll to inactive
-                let updated_poll = Poll {
-                    poll_id: p.poll_id,
-                    question: p.question.clone(),
-                    is_active: false,
-                    created_at: p.created_at,
-                };
-                poll_table.poll_id().update(updated_poll);
-            }
-        }
+    // synth-181
+    
+    #[test]
+    fn provisional_votes_do_not_count_until_confirmed() {
+        let options = [option(10, 1)];
+        let mut votes = vec![
+            vote(1, 1, "a", 10, 0),
+            Vote { provisional: true, ..vote(2, 1, "b", 10, 0) },
+        ];
+        assert_eq!(tally_results(&options, &votes)[0].vote_count, 1);
         
-        // Activate this poll by updating
-        let updated_poll = Poll {
-            poll_id,
-            question: poll.question.clone(),
-            is_active: true,
-            created_at: poll.created_at,
+        let (confirmed, delta) = with_confirmed(votes[1].clone()).unwrap();
+        assert_eq!(delta, 1);
+        votes[1] = confirmed;
+        assert_eq!(tally_results(&options, &votes)[0].vote_count, 2);
+        assert_eq!(with_confirmed(votes[1].clone()).map(|_| ()), Err(fail(E_CONFLICT, "Vote is already confirmed")));
+    }
+    
+    #[test]
+    fn confirming_a_voided_vote_keeps_it_uncounted() {
+        let voided = Vote {
+            provisional: true,
+            voided: true,
+            ..vote(1, 1, "a", 10, 0)
+        };
+        assert_eq!(with_confirmed(voided).unwrap().1, 0);
+    }
+    
+    // synth-182
+    
+    #[test]
+    fn exceeding_the_call_cap_blocks_further_calls() {
+        let mut calls = CallCount {
+            user_id: "a".to_string(),
+            recent_calls: Vec::new(),
         };
+        for i in 0..3 {
+            calls.recent_calls = recorded_call(Some(&calls), i * SEC, 3).unwrap();
+        }
+        assert_eq!(recorded_call(Some(&calls), 3 * SEC, 3), Err(fail(E_RATE_LIMITED, "Too many requests")));
         
-        poll_table.poll_id().update(updated_poll);
+        // Once the oldest call leaves the window there is room again
+        let later = CALL_WINDOW_SECS * SEC + SEC;
+        assert_eq!(recorded_call(Some(&calls), later, 3).map(|c| c.len()), Ok(2));
+        assert_eq!(recorded_call(None, 0, 3), Ok(vec![0]));
+    }
+    
+    // synth-183
+    
+    #[test]
+    fn public_export_never_carries_user_identifiers() {
+        let counts = vec![(option(10, 1), 3), (option(11, 1), 1)];
+        let named = public_rows(&poll(1), counts.clone()).unwrap();
+        let anonymous = public_rows(&Poll { anonymous_reports: true, ..poll(1) }, counts).unwrap();
+        assert_eq!(format!("{:?}", named), format!("{:?}", anonymous));
+        assert_eq!(named.iter().map(|r| r.percent).collect::<Vec<_>>(), vec![75.0, 25.0]);
         
-        // Update presentation state
-        let presentation_table = ctx.db.presentation_state();
-        if presentation_table.id().find(&0).is_some() {
-            // Update presentation state
-            let new_state = PresentationState {
-                id: 0,
-                current_poll_id: poll_id,
-                state: "voting".to_string(),
-            };
-            
-            presentation_table.id().update(new_state);
+        let users = [user("alice-id", "user"), user("bob-id", "user")];
+        let exported = format!("{:?}", named);
+        assert!(users.iter().all(|u| !exported.contains(&u.user_id)));
+    }
+    
+    #[test]
+    fn hidden_polls_are_not_exported_publicly() {
+        let draft = Poll { is_draft: true, ..poll(1) };
+        assert!(public_rows(&draft, Vec::new()).is_err());
+        let restricted = Poll { visible_to_role: Some("admin".to_string()), ..poll(1) };
+        assert!(public_rows(&restricted, Vec::new()).is_err());
+    }
+    
+    // synth-184
+    
+    #[test]
+    fn synced_counter_allocates_past_imported_ids() {
+        let imported = [vote(1, 1, "a", 10, 0), vote(500, 1, "b", 10, 0), vote(2, 1, "c", 10, 0)];
+        let highest = imported.iter().map(|v| v.vote_id).max().unwrap();
+        let counter = synced_counter(3, highest).unwrap();
+        assert!(counter + 1 > 500);
+    }
+    
+    #[test]
+    fn synced_counter_never_moves_backwards() {
+        // IDs of deleted rows must not be handed out again
+        assert_eq!(synced_counter(900, 500), None);
+        assert_eq!(synced_counter(500, 500), None);
+    }
+    
+    // synth-185
+    
+    #[test]
+    fn pinning_a_second_poll_unpins_the_first() {
+        let polls = vec![Poll { is_pinned: true, ..poll(1) }, poll(2), poll(3)];
+        let changes = pin_changes(polls, 2);
+        let pins = changes.iter().map(|p| (p.poll_id, p.is_pinned)).collect::<Vec<_>>();
+        assert_eq!(pins, vec![(1, false), (2, true)]);
+    }
+    
+    #[test]
+    fn pinned_poll_is_listed_first() {
+        let polls = vec![poll(1), poll(2), Poll { is_pinned: true, ..poll(3) }];
+        assert_eq!(listed_poll_ids(polls, &user("a", "user")), vec![3, 1, 2]);
+    }
+    
+    // synth-186
+    
+    #[test]
+    fn quorum_is_met_only_at_or_above_the_threshold() {
+        assert!(!quorum_met(Some(5), 4));
+        assert!(quorum_met(Some(5), 5));
+        assert!(quorum_met(Some(5), 6));
+        // Polls without a quorum always count
+        assert!(quorum_met(None, 0));
+    }
+    
+    // synth-187
+    
+    fn proxy(grantor_user_id: &str, proxy_user_id: &str) -> Proxy {
+        Proxy {
+            grantor_user_id: grantor_user_id.to_string(),
+            proxy_user_id: proxy_user_id.to_string(),
+            delegated_at: 0,
         }
+    }
+    
+    #[test]
+    fn proxy_vote_applies_to_both_grantors() {
+        let proxies = vec![proxy("g1", "p"), proxy("g2", "p")];
+        let votes = [vote(1, 1, "p", 10, 0)];
+        assert_eq!(grantors_to_cast(proxies, &votes), vec!["g1".to_string(), "g2".to_string()]);
+    }
+    
+    #[test]
+    fn grantor_who_voted_keeps_their_own_choice() {
+        let proxies = vec![proxy("g1", "p"), proxy("g2", "p")];
+        let votes = [vote(1, 1, "p", 10, 0), vote(2, 1, "g2", 11, 0)];
+        assert_eq!(grantors_to_cast(proxies, &votes), vec!["g1".to_string()]);
+    }
+    
+    #[test]
+    fn proxy_changing_its_vote_moves_the_grantors_too() {
+        let by_proxy = |vote_id, grantor| Vote { cast_by: Some("p".to_string()), ..vote(vote_id, 1, grantor, 10, 0) };
+        let proxies = vec![proxy("g1", "p"), proxy("g2", "p")];
+        let votes = [vote(1, 1, "p", 10, 0), by_proxy(2, "g1"), by_proxy(3, "g2")];
+        assert_eq!(grantors_to_cast(proxies, &votes), vec!["g1".to_string(), "g2".to_string()]);
+    }
+    
+    #[test]
+    fn grantor_votes_count_at_the_grantors_weight() {
+        let shares = Vote { weight: 3, cast_by: Some("p".to_string()), ..vote(2, 1, "g1", 10, 0) };
+        let votes = [vote(1, 1, "p", 10, 0), shares.clone()];
+        assert_eq!(tally_results(&[option(10, 1)], &votes)[0].vote_count, 4);
+        // Switching moves the whole weight, and re-weighting alone moves it too
+        assert_eq!(result_deltas(Some(&shares), 11, false, 3), vec![(10, -3), (11, 3)]);
+        assert_eq!(result_deltas(Some(&shares), 10, false, 5), vec![(10, -3), (10, 5)]);
+        assert_eq!(with_voided(shares, true).unwrap().1, -3);
+    }
+    
+    #[test]
+    fn delegation_cycles_are_rejected() {
+        // b -> c already exists, so a -> b is fine but c -> a would loop
+        let proxies = [proxy("a", "b"), proxy("b", "c")];
+        let proxy_of = |id: &str| proxies.iter().find(|p| p.grantor_user_id == id).map(|p| p.proxy_user_id.clone());
+        assert!(check_delegation_cycle("a", "b", proxy_of).is_ok());
+        assert_eq!(check_delegation_cycle("c", "a", proxy_of), Err(fail(E_INVALID_INPUT, "Delegation would create a cycle")));
+        assert!(check_delegation_cycle("a", "a", proxy_of).is_err());
+    }
+    
+    // synth-188
+    
+    #[test]
+    fn health_fields_populate_after_activity() {
+        let row = health_row(Some(state(4, "voting")), (12, 9), 30, false, 5 * SEC);
+        assert_eq!((row.state.as_str(), row.current_poll_id), ("voting", 4));
+        assert_eq!((row.total_users, row.connected_users, row.total_votes), (12, 9, 30));
+        assert_eq!(row.schema_version, SCHEMA_VERSION);
+        assert_eq!(row.checked_at, 5 * SEC);
+    }
+    
+    #[test]
+    fn health_reports_an_uninitialized_presentation() {
+        let row = health_row(None, (0, 0), 0, false, 0);
+        assert_eq!((row.state.as_str(), row.current_poll_id), ("uninitialized", 0));
+    }
+    
+    // synth-189
+    
+    #[test]
+    fn init_stores_the_current_schema_version() {
+        // init writes exactly this row, so a fresh database needs no migration
+        let meta = meta_row(SCHEMA_VERSION, 0);
+        assert_eq!(meta.schema_version, 1);
+        assert_eq!(migrated_version(meta.schema_version), Ok(None));
+    }
+    
+    #[test]
+    fn migration_is_idempotent() {
+        let version = migrated_version(0).unwrap();
+        assert_eq!(version, Some(SCHEMA_VERSION));
+        assert_eq!(migrated_version(SCHEMA_VERSION), Ok(None));
+        assert!(migrated_version(SCHEMA_VERSION + 1).is_err());
+    }
+    
+    // synth-190
+    
+    #[test]
+    fn raffle_draws_exactly_one_eligible_voter() {
+        let votes = vec![
+            vote(1, 1, "a", 10, 0),
+            vote(2, 1, "b", 11, 0),
+            Vote { voided: true, ..vote(3, 1, "c", 10, 0) },
+            vote(4, 1, "d", 10, 0),
+        ];
+        let raffle = raffle_draw(1, votes.clone(), 7 * SEC).unwrap();
+        assert_eq!(raffle.entrant_count, 3);
+        assert!(["a", "b", "d"].contains(&raffle.winner_user_id.as_str()));
         
-        Ok(())
-    } else {
-        Err("Poll not found".to_string())
+        // The same inputs give the same winner, whatever order they come in
+        let mut shuffled = votes;
+        shuffled.reverse();
+        let again = raffle_draw(1, shuffled, 7 * SEC).unwrap();
+        assert_eq!((again.winner_user_id, again.seed), (raffle.winner_user_id, raffle.seed));
     }
-}
-
-#[reducer]
-pub fn submit_vote(ctx: &ReducerContext, poll_id: u64, option_id: u64) -> Result<(), String> {
-    // Check if user exists
-    let user_table = ctx.db.user();
-    if user_table.user_id().find(&ctx.sender.to_string()).is_none() {
-        return Err("User not found".to_string());
+    
+    #[test]
+    fn raffle_needs_an_eligible_voter() {
+        let votes = vec![Vote { is_demo: true, ..vote(1, 1, "demo", 10, 0) }];
+        assert_eq!(raffle_draw(1, votes, 0).map(|_| ()), Err(fail(E_CONFLICT, "No eligible voters")));
     }
     
-    // Check if poll exists and is active
-    let poll_table = ctx.db.poll();
-    if let Some(poll) = poll_table.poll_id().find(&poll_id) {
-        if !poll.is_active {
-            return Err("Poll is not active".to_string());
+    // synth-191
+    
+    fn choices(user_id: &str, option_ids: Vec<u64>) -> ChoiceBallot {
+        ChoiceBallot {
+            ballot_id: 0,
+            poll_id: 1,
+            user_id: user_id.to_string(),
+            option_ids: normalized_choices(option_ids).unwrap(),
+            submitted_at: 0,
         }
-    } else {
-        return Err("Poll not found".to_string());
     }
     
-    // Check if option exists for this poll
-    let option_table = ctx.db.poll_option();
-    let option_exists = option_table.iter()
-        .any(|o| o.poll_id == poll_id && o.option_id == option_id);
-    if !option_exists {
-        return Err("Option not found for this poll".to_string());
+    #[test]
+    fn co_selection_counts_each_pair_picked_together() {
+        let ballots = vec![
+            choices("a", vec![12, 10, 11]),
+            choices("b", vec![11, 10, 10]),
+            choices("c", vec![12]),
+        ];
+        assert_eq!(co_selection_pairs(ballots.into_iter()), vec![(10, 11, 2), (10, 12, 1), (11, 12, 1)]);
     }
     
-    // Look for existing vote
-    let vote_table = ctx.db.vote();
-    let user_id = ctx.sender.to_string();
+    #[test]
+    fn choice_ballots_are_sorted_deduplicated_and_non_empty() {
+        assert_eq!(normalized_choices(vec![3, 1, 3, 2]), Ok(vec![1, 2, 3]));
+        assert_eq!(normalized_choices(Vec::new()), Err(fail(E_INVALID_INPUT, "Select at least one option")));
+    }
+    
+    // synth-192
+    
+    fn error_code(err: &str) -> &str {
+        err.split(": ").next().unwrap_or_default()
+    }
+    
+    #[test]
+    fn errors_lead_with_a_stable_code() {
+        assert_eq!(fail(E_NOT_FOUND, "Poll not found"), "E_NOT_FOUND: Poll not found");
+        assert_eq!(fail(E_INVALID_INPUT, format!("at most {} options", 3)), "E_INVALID_INPUT: at most 3 options");
+    }
     
-    // Find existing vote by filtering through all votes for this user and poll
-    let mut existing_vote_id = None;
-    for vote in vote_table.iter() {
-        if vote.poll_id == poll_id && vote.user_id == user_id {
-            existing_vote_id = Some(vote.vote_id);
-            break;
+    #[test]
+    fn failure_paths_report_their_codes() {
+        let anonymous = Poll { anonymous_reports: true, ..poll(1) };
+        let private = Poll { public_ballots: false, ..poll(1) };
+        let sealed = Settings { sealed: true, ..default_settings() };
+        let cases = [
+            (check_ballot_access(&anonymous, &user("a", "admin")), E_CONFLICT),
+            (check_ballot_access(&private, &user("a", "user")), E_FORBIDDEN),
+            (option_for_poll(None, 1).map(|_| ()), E_NOT_FOUND),
+            (check_not_sealed(&sealed), E_SEALED),
+            (voting_open_at(&poll(1), 0, 0), E_CLOSED),
+        ];
+        for (result, code) in cases {
+            assert_eq!(error_code(&result.unwrap_err()), code);
         }
     }
     
-    if let Some(vote_id) = existing_vote_id {
-        // Update existing vote
-        let updated_vote = Vote {
-            vote_id,
-            poll_id,
-            user_id,
-            option_id,
-            voted_at: ctx.timestamp.to_micros_since_unix_epoch(),
+    // synth-193
+    
+    #[test]
+    fn untranslated_options_fall_back_to_the_original() {
+        let options = vec![text_option(10, 1, "Red"), text_option(11, 1, "Blue")];
+        let localized = localized_options(options, |option_id| (option_id == 10).then(|| "Rojo".to_string()));
+        let texts = localized.iter().map(|o| (o.text.as_str(), o.translated)).collect::<Vec<_>>();
+        assert_eq!(texts, vec![("Rojo", true), ("Blue", false)]);
+    }
+    
+    #[test]
+    fn language_codes_are_validated_and_lowercased() {
+        assert_eq!(normalize_lang("pt-BR"), Ok("pt-br".to_string()));
+        assert_eq!(normalize_lang("EN"), Ok("en".to_string()));
+        assert!(normalize_lang("english").is_err());
+        assert!(normalize_lang("e1").is_err());
+    }
+    
+    // synth-194
+    
+    #[test]
+    fn admin_cap_blocks_one_admin_too_many() {
+        let mut users = vec![user("host", "admin"), user("a", "user")];
+        assert!(check_admin_cap(2, users.clone().into_iter(), "default").is_ok());
+        users.push(user("co-host", "admin"));
+        assert_eq!(check_admin_cap(2, users.clone().into_iter(), "default"), Err(fail(E_CONFLICT, "Admin limit reached")));
+        // Other sessions and disconnected admins don't take a slot
+        assert!(check_admin_cap(2, users.clone().into_iter(), "other").is_ok());
+        users[2].connected = false;
+        assert!(check_admin_cap(2, users.clone().into_iter(), "default").is_ok());
+        assert!(check_admin_cap(0, users.into_iter(), "default").is_ok());
+    }
+    
+    // synth-195
+    
+    #[test]
+    fn report_has_a_winner_line_for_each_finalized_poll() {
+        let finalized = |poll_id, question: &str| Poll {
+            question: question.to_string(),
+            closed_at: Some(poll_id as i64 * SEC),
+            ..poll(poll_id)
         };
+        let polls = vec![finalized(2, "Lunch?"), finalized(1, "Colour?"), Poll { is_active: true, ..poll(3) }];
+        let (text, poll_count) = report_text(polls, |poll_id| match poll_id {
+            1 => vec![(text_option(10, 1, "Red"), 3), (text_option(11, 1, "Blue"), 1)],
+            _ => vec![(text_option(20, 2, "Pizza"), 2), (text_option(21, 2, "Soup"), 2)],
+        });
+        assert_eq!(poll_count, 2);
+        assert!(text.contains("1. Colour?\n   Winner: Red (75.0%)"));
+        assert!(text.contains("2. Lunch?\n   Tie: Pizza, Soup (50.0% each)"));
+    }
+    
+    #[test]
+    fn report_without_finalized_polls_says_so() {
+        let (text, poll_count) = report_text(vec![poll(1)], |_| Vec::new());
+        assert_eq!(poll_count, 0);
+        assert!(text.ends_with("No polls have been finalized yet."));
+    }
+    
+    // synth-196
+    
+    #[test]
+    fn resetting_a_user_removes_only_their_counted_votes() {
+        let votes = [
+            vote(1, 1, "a", 10, 0),
+            vote(2, 2, "a", 20, 0),
+            Vote { voided: true, ..vote(3, 3, "a", 30, 0) },
+            vote(4, 1, "b", 10, 0),
+        ];
+        let theirs = votes.iter().filter(|v| v.user_id == "a").cloned().collect::<Vec<_>>();
+        assert_eq!(vote_removals(&theirs), vec![(1, 10, -1), (2, 20, -1)]);
         
-        vote_table.vote_id().update(updated_vote);
-    } else {
-        // Create new vote
-        let all_votes = vote_table.iter().collect::<Vec<_>>();
-        let vote_id = (all_votes.len() + 1) as u64;
-        
-        let vote = Vote {
-            vote_id,
-            poll_id,
-            user_id,
-            option_id,
-            voted_at: ctx.timestamp.to_micros_since_unix_epoch(),
-        };
+        // The rest of the poll still counts; the user row itself is untouched
+        let remaining = votes.iter().filter(|v| v.user_id != "a").cloned().collect::<Vec<_>>();
+        assert_eq!(tally_results(&[option(10, 1)], &remaining)[0].vote_count, 1);
+    }
+    
+    // synth-197
+    
+    #[test]
+    fn two_dominant_options_count_as_about_two() {
+        let effective = inverse_simpson(&[48, 48, 2, 1, 1]);
+        assert!((effective - 2.0).abs() < 0.25, "{}", effective);
+    }
+    
+    #[test]
+    fn effective_options_spans_one_to_the_option_count() {
+        assert_eq!(inverse_simpson(&[9, 0, 0]), 1.0);
+        assert!((inverse_simpson(&[3, 3, 3]) - 3.0).abs() < 1e-9);
+        assert_eq!(inverse_simpson(&[0, 0]), 0.0);
+    }
+    
+    // synth-198
+    
+    #[test]
+    fn seeded_demo_votes_match_the_distribution() {
+        let distribution = [
+            DemoVoteCount { option_id: 10, count: 3 },
+            DemoVoteCount { option_id: 11, count: 1 },
+        ];
+        assert_eq!(demo_vote_total(&distribution), Ok(4));
+        let votes = demo_votes(1, &distribution, 100, 5 * SEC);
+        let counts = tally_results(&[option(10, 1), option(11, 1), option(12, 1)], &votes).iter()
+            .map(|r| r.vote_count)
+            .collect::<Vec<_>>();
+        assert_eq!(counts, vec![3, 1, 0]);
         
-        vote_table.insert(vote);
+        assert!(votes.iter().all(|v| v.is_demo));
+        let mut voters = votes.iter().map(|v| v.user_id.as_str()).collect::<Vec<_>>();
+        voters.dedup();
+        assert_eq!(voters, vec!["demo-100", "demo-101", "demo-102", "demo-103"]);
     }
     
-    Ok(())
-}
-
-#[reducer]
-pub fn show_results(ctx: &ReducerContext) -> Result<(), String> {
-    // Check if user is admin
-    let user_table = ctx.db.user();
-    if let Some(user) = user_table.user_id().find(&ctx.sender.to_string()) {
-        if user.role != "admin" {
-            return Err("Only admins can show results".to_string());
-        }
-    } else {
-        return Err("User not found".to_string());
+    #[test]
+    fn demo_seeding_is_capped() {
+        let distribution = [DemoVoteCount { option_id: 10, count: MAX_DEMO_VOTES as u32 + 1 }];
+        assert!(demo_vote_total(&distribution).is_err());
     }
     
-    // Update presentation state
-    let presentation_table = ctx.db.presentation_state();
-    if let Some(old_state) = presentation_table.id().find(&0) {
-        let new_state = PresentationState {
-            id: 0,
-            current_poll_id: old_state.current_poll_id,
-            state: "results".to_string(),
-        };
+    // synth-199
+    
+    #[test]
+    fn closed_poll_rejections_are_logged_only_when_enabled() {
+        let reason = voting_open_at(&poll(1), 0, 0).unwrap_err();
+        let on = Settings { log_rejected: true, ..default_settings() };
+        let off = Settings { log_rejected: false, ..default_settings() };
         
-        presentation_table.id().update(new_state);
-        Ok(())
-    } else {
-        Err("Presentation state not found".to_string())
+        let attempt = rejected_attempt(&on, "a".to_string(), 1, reason.clone(), 3 * SEC).unwrap();
+        assert_eq!((attempt.user_id.as_str(), attempt.poll_id, attempt.attempted_at), ("a", 1, 3 * SEC));
+        assert_eq!(attempt.reason, reason);
+        assert!(rejected_attempt(&off, "a".to_string(), 1, reason, 3 * SEC).is_none());
     }
-}
-
-#[reducer]
-pub fn end_session(ctx: &ReducerContext) -> Result<(), String> {
-    // Check if user is admin
-    let user_table = ctx.db.user();
-    if let Some(user) = user_table.user_id().find(&ctx.sender.to_string()) {
-        if user.role != "admin" {
-            return Err("Only admins can end the session".to_string());
-        }
-    } else {
-        return Err("User not found".to_string());
+    
+    #[test]
+    fn other_rejections_are_never_logged() {
+        let on = Settings { log_rejected: true, ..default_settings() };
+        let reason = fail(E_NOT_FOUND, "Option not found for this poll");
+        assert!(rejected_attempt(&on, "a".to_string(), 1, reason, 0).is_none());
     }
     
-    // Update presentation state
-    let presentation_table = ctx.db.presentation_state();
-    if let Some(old_state) = presentation_table.id().find(&0) {
-        let new_state = PresentationState {
-            id: 0,
-            current_poll_id: old_state.current_poll_id,
-            state: "ended".to_string(),
+    // synth-200
+    
+    #[test]
+    fn early_and_late_cohorts_show_different_turnout() {
+        let joined = |user_id, at| User {
+            connected_at: at,
+            ..user(user_id, "user")
+        };
+        let closed = Poll {
+            activated_at: Some(10 * SEC),
+            closed_at: Some(20 * SEC),
+            ..poll(1)
         };
+        let users = [joined("a", 5 * SEC), joined("b", 6 * SEC), joined("c", 15 * SEC), joined("d", 25 * SEC)];
+        let votes = [vote(1, 1, "a", 10, 0), vote(2, 1, "b", 10, 0), vote(3, 1, "d", 10, 0)];
+        assert_eq!(cohort_tallies(&closed, users.clone().into_iter(), &votes), vec![
+            ("before_open", 2, 2),
+            ("while_open", 1, 0),
+            ("after_close", 1, 1),
+        ]);
         
-        presentation_table.id().update(new_state);
+        // Without an activation time everyone shares one cohort
+        assert_eq!(cohort_tallies(&poll(1), users.into_iter(), &votes), vec![("not_opened", 4, 3)]);
+    }
+    
+    // synth-201
+    
+    #[test]
+    fn scheduled_flip_transitions_to_results() {
+        let revealing = revealing_state(state(1, "voting"), 10 * SEC, 5).unwrap();
+        assert_eq!((revealing.state.as_str(), revealing.reveal_at), ("revealing", Some(15 * SEC)));
         
-        // Deactivate all polls
-        let poll_table = ctx.db.poll();
-        for poll in poll_table.iter() {
-            if poll.is_active {
-                // Update poll to inactive
-                let updated_poll = Poll {
-                    poll_id: poll.poll_id,
-                    question: poll.question.clone(),
-                    is_active: false,
-                    created_at: poll.created_at,
-                };
-                
-                poll_table.poll_id().update(updated_poll);
-            }
-        }
+        let revealed = revealed_state(revealing, 15 * SEC).unwrap();
+        assert_eq!((revealed.state.as_str(), revealed.reveal_at, revealed.current_poll_id), ("results", None, 1));
+    }
+    
+    #[test]
+    fn stale_or_cancelled_countdowns_do_not_flip() {
+        let revealing = revealing_state(state(1, "voting"), 10 * SEC, 5).unwrap();
+        // A restarted countdown leaves the first schedule behind
+        assert!(revealed_state(revealing.clone(), 12 * SEC).is_none());
         
-        Ok(())
-    } else {
-        Err("Presentation state not found".to_string())
+        let cancelled = cancelled_reveal(revealing, true);
+        assert_eq!((cancelled.state.as_str(), cancelled.reveal_at), ("voting", None));
+        assert!(revealed_state(cancelled, 15 * SEC).is_none());
+    }
+    
+    #[test]
+    fn countdown_length_and_session_state_are_checked() {
+        assert!(revealing_state(state(1, "voting"), 0, 0).is_err());
+        assert!(revealing_state(state(1, "voting"), 0, MAX_REVEAL_SECS + 1).is_err());
+        assert_eq!(revealing_state(state(1, "ended"), 0, 5).map(|_| ()), Err(fail(E_CLOSED, "Session has ended")));
     }
-}
-
-// ======== Initial Setup ========
-
-#[reducer(init)]
-pub fn init(ctx: &ReducerContext) {
-    // Initialize the presentation state
-    let initial_state = PresentationState {
-        id: 0,
-        current_poll_id: 0,
-        state: "waiting".to_string(),
-    };
-    ctx.db.presentation_state().insert(initial_state);
 }